@@ -1,7 +1,38 @@
 // Game of life compute
 
+// birth and survival counts are matched by bitmask or by inclusive range
+struct Rule {
+    birth_mask: u32,
+    survival_mask: u32,
+    birth_min: u32,
+    birth_max: u32,
+    survival_min: u32,
+    survival_max: u32,
+    states: u32,
+    radius: u32,
+    include_center: u32,
+    // uniform array elements are 16 bytes aligned
+    padding_0: u32,
+    padding_1: u32,
+    padding_2: u32,
+}
+
+// live cells within radius of the segment, in texels
+struct Stamp {
+    point_a: vec2<f32>,
+    point_b: vec2<f32>,
+    radius: f32,
+    channel: u32,
+    pattern: u32, // 0 solid, 1 random, 2 checker
+    density: f32,
+}
+
 struct Settings {
     rng_seed: u32,
+    stamp_count: u32,
+    stamp_seed: u32,
+    rules: array<Rule, 3>,
+    stamps: array<Stamp, 16>,
 }
 
 @group(0) @binding(0) var input: texture_storage_2d<rgba32float, read>;
@@ -28,21 +59,54 @@ fn random_float_bb(value: u32) -> f32 {
     return f32(hash(hash(value))) / 4294967295.0;
 }
 
-fn is_alive(location: vec2<i32>, offset_x: i32, offset_y: i32, index: u32) -> u32 {
-    let value: vec4<f32> = textureLoad(input, location + vec2<i32>(offset_x, offset_y));
-    return u32(value[index]);
+// alive is 1, dying states fade towards 0, dead is 0
+fn decode_state(value: f32, states: u32) -> u32 {
+    if (value >= 1.0) {
+        return 1u;
+    }
+    if (value <= 0.0 || states <= 2u) {
+        return 0u;
+    }
+    let dying = u32(round((1.0 - value) * f32(states - 1u))) + 1u;
+    return min(dying, states - 1u);
+}
+
+fn encode_state(state: u32, states: u32) -> f32 {
+    if (state == 0u) {
+        return 0.0;
+    }
+    return 1.0 - f32(state - 1u) / f32(states - 1u);
 }
 
-fn count_alive_neighbors(location: vec2<i32>, index: u32) -> u32 {
-    return
-        is_alive(location, -1, -1, index) +
-        is_alive(location, -1,  0, index) +
-        is_alive(location, -1,  1, index) +
-        is_alive(location,  0, -1, index) +
-        is_alive(location,  0,  1, index) +
-        is_alive(location,  1, -1, index) +
-        is_alive(location,  1,  0, index) +
-        is_alive(location,  1,  1, index);
+fn in_counts(count: u32, mask: u32, min_count: u32, max_count: u32) -> bool {
+    let in_mask = count < 32u && ((mask >> count) & 1u) == 1u;
+    return in_mask || (count >= min_count && count <= max_count);
+}
+
+// alive neighbors of every channel, gathered in a single sweep over the largest radius
+fn count_alive_neighbors(location: vec2<i32>) -> vec3<u32> {
+    var radii = vec3<i32>(0);
+    for (var ii: u32 = 0; ii < 3; ii++) {
+        radii[ii] = i32(settings.rules[ii].radius);
+    }
+    let radius = max(radii.x, max(radii.y, radii.z));
+
+    var counts = vec3<u32>(0u);
+    for (var dy: i32 = -radius; dy <= radius; dy++) {
+        for (var dx: i32 = -radius; dx <= radius; dx++) {
+            let value = textureLoad(input, location + vec2<i32>(dx, dy));
+            let distance = max(abs(dx), abs(dy));
+            for (var ii: u32 = 0; ii < 3; ii++) {
+                let is_center = dx == 0 && dy == 0;
+                let is_counted = distance <= radii[ii]
+                    && (!is_center || settings.rules[ii].include_center != 0u);
+                if (is_counted && value[ii] >= 1.0) {
+                    counts[ii] += 1u;
+                }
+            }
+        }
+    }
+    return counts;
 }
 
 @compute @workgroup_size(8, 8, 1)
@@ -62,27 +126,78 @@ fn init(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
 
 @compute @workgroup_size(8, 8, 1)
 fn update(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
-    var location = vec2<i32>(i32(invocation_id.x), i32(invocation_id.y));
-    location.x += i32(settings.rng_seed);
-    location.x -= i32(settings.rng_seed);
+    let location = vec2<i32>(i32(invocation_id.x), i32(invocation_id.y));
 
-    var color : vec4<f32> = textureLoad(input, location);
+    var color: vec4<f32> = textureLoad(input, location);
+    let counts = count_alive_neighbors(location);
 
     for (var ii: u32 = 0; ii < 3; ii++)
     {
-        let num_alive_neighbors = count_alive_neighbors(location, ii);
-    
-        var next_alive: bool = false;
-        if (num_alive_neighbors == 3) {
-            next_alive = true;
-        } else if (num_alive_neighbors == 2) {
-            let current_alive = bool(is_alive(location, 0, 0, ii));
-            next_alive = current_alive;
+        let rule = settings.rules[ii];
+        let state = decode_state(color[ii], rule.states);
+        let count = counts[ii];
+
+        var next_state: u32 = 0u;
+        if (state == 0u) {
+            if (in_counts(count, rule.birth_mask, rule.birth_min, rule.birth_max)) {
+                next_state = 1u;
+            }
+        } else if (state == 1u) {
+            if (in_counts(count, rule.survival_mask, rule.survival_min, rule.survival_max)) {
+                next_state = 1u;
+            } else if (rule.states > 2u) {
+                next_state = 2u;
+            }
         } else {
-            next_alive = false;
+            next_state = (state + 1u) % rule.states;
+        }
+
+        color[ii] = encode_state(next_state, rule.states);
+    }
+
+    textureStore(output, location, color);
+}
+
+fn segment_distance(point: vec2<f32>, point_a: vec2<f32>, point_b: vec2<f32>) -> f32 {
+    let delta = point_b - point_a;
+    let length_squared = dot(delta, delta);
+    var ratio = 0.0;
+    if (length_squared > 0.0) {
+        ratio = clamp(dot(point - point_a, delta) / length_squared, 0.0, 1.0);
+    }
+    return distance(point, point_a + delta * ratio);
+}
+
+fn is_stamped(location: vec2<i32>, stamp: Stamp) -> bool {
+    switch stamp.pattern {
+        case 1u: {
+            let index = u32(location.y) << 16u | u32(location.x);
+            return f32(hash(index ^ (settings.stamp_seed * 2654435769u))) / 4294967295.0 < stamp.density;
+        }
+        case 2u: {
+            return ((location.x + location.y) & 1) == 0;
+        }
+        default: {
+            return true;
+        }
+    }
+}
+
+// copies the input with the brush stamps applied
+@compute @workgroup_size(8, 8, 1)
+fn paint(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let location = vec2<i32>(i32(invocation_id.x), i32(invocation_id.y));
+    let center = vec2<f32>(location) + 0.5;
+
+    var color: vec4<f32> = textureLoad(input, location);
+
+    for (var kk: u32 = 0; kk < settings.stamp_count; kk++)
+    {
+        let stamp = settings.stamps[kk];
+        let is_inside = segment_distance(center, stamp.point_a, stamp.point_b) <= stamp.radius;
+        if (is_inside && is_stamped(location, stamp)) {
+            color[stamp.channel] = 1.0;
         }
-    
-        color[ii] = f32(next_alive);
     }
 
     textureStore(output, location, color);
@@ -0,0 +1,116 @@
+//! Compares the kd-tree backed spatial index against brute force
+//!
+//! cargo bench --bench spatial_index
+
+// the crate has no library target, the index module is compiled in directly
+#[path = "../src/spatial/index.rs"]
+mod index;
+
+use bevy::prelude::{Entity, Vec3};
+use index::{IndexedPoint, SpatialIndex};
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const QUERY_COUNT: usize = 1000;
+const QUERY_RADIUS: f32 = 10.0;
+
+/// Cheap xorshift, deterministic across runs
+fn random(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state as f32 / u32::MAX as f32 * 200.0 - 100.0
+}
+
+fn random_position(state: &mut u32) -> Vec3 {
+    Vec3::new(random(state), random(state), random(state))
+}
+
+fn measure(mut func: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    func();
+    start.elapsed()
+}
+
+fn nearest_brute_force(points: &[IndexedPoint], position: Vec3) -> Option<(Entity, f32)> {
+    points
+        .iter()
+        .map(|point| (point.entity, point.position.distance(position)))
+        .min_by(|aa, bb| aa.1.total_cmp(&bb.1))
+}
+
+fn within_radius_brute_force(points: &[IndexedPoint], position: Vec3, radius: f32) -> Vec<Entity> {
+    points
+        .iter()
+        .filter(|point| point.position.distance(position) < radius)
+        .map(|point| point.entity)
+        .collect()
+}
+
+fn main() {
+    let mut state = 0x9e3779b9;
+    println!(
+        "{:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "count", "build", "kd nearest", "brute force", "kd radius", "brute force", "speedup"
+    );
+    for count in [500, 1000, 2000, 4000, 8000] {
+        let points: Vec<IndexedPoint> = (0..count)
+            .map(|index| IndexedPoint {
+                position: random_position(&mut state),
+                entity: Entity::from_raw(index),
+            })
+            .collect();
+        let queries: Vec<Vec3> = (0..QUERY_COUNT)
+            .map(|_| random_position(&mut state))
+            .collect();
+
+        let mut index = SpatialIndex::default();
+        let build = measure(|| index = SpatialIndex::from_points(points.clone()));
+        assert_eq!(index.len(), points.len());
+
+        let kd_nearest = measure(|| {
+            for query in &queries {
+                black_box(index.k_nearest(*query, 1));
+            }
+        });
+        let brute_nearest = measure(|| {
+            for query in &queries {
+                black_box(nearest_brute_force(&points, *query));
+            }
+        });
+        let kd_radius = measure(|| {
+            for query in &queries {
+                black_box(index.within_radius(*query, QUERY_RADIUS));
+            }
+        });
+        let brute_radius = measure(|| {
+            for query in &queries {
+                black_box(within_radius_brute_force(&points, *query, QUERY_RADIUS));
+            }
+        });
+
+        // both agree on the distance, ties may pick different entities
+        for query in &queries {
+            let (_, aa) = index.k_nearest(*query, 1)[0];
+            let (_, bb) = nearest_brute_force(&points, *query).unwrap();
+            assert!((aa - bb).abs() < 1e-3);
+            let mut aa = index.within_radius(*query, QUERY_RADIUS);
+            let mut bb = within_radius_brute_force(&points, *query, QUERY_RADIUS);
+            aa.sort();
+            bb.sort();
+            assert_eq!(aa, bb);
+        }
+
+        println!(
+            "{:>8} {:>12?} {:>12?} {:>12?} {:>12?} {:>12?} {:>11.1}x",
+            count,
+            build,
+            kd_nearest,
+            brute_nearest,
+            kd_radius,
+            brute_radius,
+            (brute_nearest + brute_radius).as_secs_f64() / (kd_nearest + kd_radius).as_secs_f64()
+        );
+    }
+}
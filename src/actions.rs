@@ -0,0 +1,425 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(not(target_family = "wasm"))]
+const BINDINGS_PATH: &str = "input_bindings.ron";
+
+/// Gameplay intents, decoupled from the devices producing them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Thrust,        // [0, 1]
+    Brake,         // [0, 1]
+    Turn,          // [-1, 1], positive turns left
+    MoveTarget,    // [-1, 1]^2
+    ToggleCapture, // button
+    Reset,         // button
+    Reseed,        // button
+    ToggleCrawl,   // button, attaches to and detaches from walls
+    PickTarget,    // button, picks the ground under the cursor
+    QueueTarget,   // modifier, queues picked targets instead of replacing them
+    PauseSimu,     // button, pauses and resumes the simulation
+    StepSimu,      // button, advances the paused simulation by a generation
+    SlowerSimu,    // button
+    FasterSimu,    // button
+    DumpSnapshot,  // button, writes the next simulation readback to disk
+    DumpNavGrid,   // button, writes the nav grid to disk
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    KeyAxis {
+        negative: KeyCode,
+        positive: KeyCode,
+    },
+    Mouse(MouseButton),
+    GamepadButton(GamepadButton), // analog for the triggers
+    GamepadAxis {
+        axis: GamepadAxis,
+        inverted: bool,
+    },
+    GamepadStick {
+        x: GamepadAxis,
+        y: GamepadAxis,
+    },
+}
+
+/// Shapes the magnitude of an analog input once past the deadzone
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    Power(f32), // > 1 gives finer control around the center
+}
+
+impl ResponseCurve {
+    fn apply(&self, value: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Power(exponent) => value.powf(*exponent),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActionBinding {
+    pub bindings: Vec<Binding>,
+    #[serde(default)]
+    pub deadzone: f32, // magnitude below which the input is ignored
+    #[serde(default)]
+    pub curve: ResponseCurve,
+}
+
+impl ActionBinding {
+    fn new(bindings: Vec<Binding>) -> Self {
+        Self {
+            bindings,
+            deadzone: 0.0,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    fn with_analog(mut self, deadzone: f32, curve: ResponseCurve) -> Self {
+        self.deadzone = deadzone;
+        self.curve = curve;
+        self
+    }
+
+    /// Radial deadzone, the remaining range is rescaled to [0, 1] before the curve
+    fn shape(&self, value: Vec2) -> Vec2 {
+        let magnitude = value.length();
+        if magnitude <= self.deadzone || magnitude <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let rescaled = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).clamp(0.0, 1.0);
+        value / magnitude * self.curve.apply(rescaled)
+    }
+}
+
+/// Per action bindings, saved to the working directory on native builds
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct InputBindings(pub BTreeMap<Action, ActionBinding>);
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let stick = ResponseCurve::Power(2.0);
+        Self(BTreeMap::from([
+            (
+                Action::Thrust,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::ArrowUp),
+                    Binding::GamepadButton(GamepadButton::RightTrigger2),
+                ])
+                .with_analog(0.05, ResponseCurve::Linear),
+            ),
+            (
+                Action::Brake,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::ArrowDown),
+                    Binding::GamepadButton(GamepadButton::LeftTrigger2),
+                ])
+                .with_analog(0.05, ResponseCurve::Linear),
+            ),
+            (
+                Action::Turn,
+                ActionBinding::new(vec![
+                    Binding::KeyAxis {
+                        negative: KeyCode::ArrowRight,
+                        positive: KeyCode::ArrowLeft,
+                    },
+                    Binding::GamepadAxis {
+                        axis: GamepadAxis::RightStickX,
+                        inverted: true,
+                    },
+                ])
+                .with_analog(0.05, stick),
+            ),
+            (
+                Action::MoveTarget,
+                ActionBinding::new(vec![Binding::GamepadStick {
+                    x: GamepadAxis::LeftStickX,
+                    y: GamepadAxis::LeftStickY,
+                }])
+                .with_analog(0.05, stick),
+            ),
+            (
+                Action::ToggleCapture,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::Space),
+                    Binding::Mouse(MouseButton::Middle),
+                    Binding::GamepadButton(GamepadButton::South),
+                ]),
+            ),
+            (
+                Action::Reset,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::KeyR),
+                    Binding::GamepadButton(GamepadButton::Select),
+                ]),
+            ),
+            (
+                Action::Reseed,
+                ActionBinding::new(vec![
+                    // space reinitialized the simulation before actions existed
+                    Binding::Key(KeyCode::Space),
+                    Binding::Key(KeyCode::KeyT),
+                    Binding::GamepadButton(GamepadButton::North),
+                ]),
+            ),
+            (
+                Action::ToggleCrawl,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::KeyC),
+                    Binding::GamepadButton(GamepadButton::West),
+                ]),
+            ),
+            (
+                Action::PickTarget,
+                ActionBinding::new(vec![Binding::Mouse(MouseButton::Left)]),
+            ),
+            (
+                Action::QueueTarget,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::ShiftLeft),
+                    Binding::Key(KeyCode::ShiftRight),
+                ]),
+            ),
+            (
+                Action::PauseSimu,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::KeyP),
+                    Binding::GamepadButton(GamepadButton::DPadUp),
+                ]),
+            ),
+            (
+                Action::StepSimu,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::Period),
+                    Binding::GamepadButton(GamepadButton::DPadDown),
+                ]),
+            ),
+            (
+                Action::SlowerSimu,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::Minus),
+                    Binding::GamepadButton(GamepadButton::DPadLeft),
+                ]),
+            ),
+            (
+                Action::FasterSimu,
+                ActionBinding::new(vec![
+                    Binding::Key(KeyCode::Equal),
+                    Binding::GamepadButton(GamepadButton::DPadRight),
+                ]),
+            ),
+            (
+                Action::DumpSnapshot,
+                ActionBinding::new(vec![Binding::Key(KeyCode::KeyM)]),
+            ),
+            (
+                Action::DumpNavGrid,
+                ActionBinding::new(vec![Binding::Key(KeyCode::KeyN)]),
+            ),
+        ]))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ActionValue {
+    value: Vec2,
+    pressed: bool,
+    just_pressed: bool,
+}
+
+/// Device, or group of devices, a player drives with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputSource {
+    KeyboardMouse,
+    Gamepad(Entity),
+}
+
+/// Values of every action for the current frame
+#[derive(Default, Debug)]
+pub struct ActionValues {
+    values: BTreeMap<Action, ActionValue>,
+}
+
+impl ActionValues {
+    pub fn axis(&self, action: Action) -> f32 {
+        self.axis_pair(action).x
+    }
+
+    pub fn axis_pair(&self, action: Action) -> Vec2 {
+        self.values
+            .get(&action)
+            .map(|value| value.value)
+            .unwrap_or_default()
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.values.get(&action).is_some_and(|value| value.pressed)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.values
+            .get(&action)
+            .is_some_and(|value| value.just_pressed)
+    }
+
+    fn update(&mut self, action: Action, value: Vec2) {
+        let previous = self.values.get(&action).copied().unwrap_or_default();
+        let pressed = value.length() > 0.5;
+        self.values.insert(
+            action,
+            ActionValue {
+                value,
+                pressed,
+                just_pressed: pressed && !previous.pressed,
+            },
+        );
+    }
+}
+
+/// Actions per input source, and merged over every source for the shared actions
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pub any: ActionValues,
+    sources: HashMap<InputSource, ActionValues>,
+}
+
+impl ActionState {
+    pub fn source(&self, source: InputSource) -> Option<&ActionValues> {
+        self.sources.get(&source)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_bindings());
+        app.init_resource::<ActionState>();
+        app.add_systems(PreUpdate, update_action_state.after(InputSystem));
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn load_bindings() -> InputBindings {
+    InputBindings::default()
+}
+
+/// Reads the bindings file, writes the defaults when there is none yet
+#[cfg(not(target_family = "wasm"))]
+fn load_bindings() -> InputBindings {
+    match std::fs::read_to_string(BINDINGS_PATH) {
+        Ok(text) => match ron::from_str::<InputBindings>(&text) {
+            Ok(mut bindings) => {
+                info!("input bindings loaded from {}", BINDINGS_PATH);
+                // actions added since the file was saved keep their default bindings
+                for (action, binding) in InputBindings::default().0 {
+                    bindings.0.entry(action).or_insert(binding);
+                }
+                return bindings;
+            }
+            Err(err) => error!("could not parse {}: {}", BINDINGS_PATH, err),
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let bindings = InputBindings::default();
+            let result = ron::ser::to_string_pretty(&bindings, ron::ser::PrettyConfig::default())
+                .map_err(|err| err.to_string())
+                .and_then(|text| {
+                    std::fs::write(BINDINGS_PATH, text).map_err(|err| err.to_string())
+                });
+            match result {
+                Ok(()) => info!("default input bindings saved to {}", BINDINGS_PATH),
+                Err(err) => error!("could not save {}: {}", BINDINGS_PATH, err),
+            }
+            return bindings;
+        }
+        Err(err) => error!("could not read {}: {}", BINDINGS_PATH, err),
+    }
+    InputBindings::default()
+}
+
+/// Value of a binding for a given source, zero when the binding targets another device
+fn binding_value(
+    binding: &Binding,
+    source: InputSource,
+    keyboard: &ButtonInput<KeyCode>,
+    mouse: &ButtonInput<MouseButton>,
+    gamepads: &Query<(Entity, &Gamepad)>,
+) -> Vec2 {
+    let key = |code: &KeyCode| if keyboard.pressed(*code) { 1.0 } else { 0.0 };
+    let gamepad = match source {
+        InputSource::Gamepad(entity) => gamepads.get(entity).ok().map(|(_, gamepad)| gamepad),
+        InputSource::KeyboardMouse => None,
+    };
+    let is_keyboard = source == InputSource::KeyboardMouse;
+    match (binding, gamepad) {
+        (Binding::Key(code), _) if is_keyboard => Vec2::X * key(code),
+        (Binding::KeyAxis { negative, positive }, _) if is_keyboard => {
+            Vec2::X * (key(positive) - key(negative))
+        }
+        (Binding::Mouse(button), _) if is_keyboard => {
+            Vec2::X * if mouse.pressed(*button) { 1.0 } else { 0.0 }
+        }
+        (Binding::GamepadButton(button), Some(gamepad)) => {
+            Vec2::X * gamepad.get(*button).unwrap_or(0.0)
+        }
+        (Binding::GamepadAxis { axis, inverted }, Some(gamepad)) => {
+            let value = gamepad.get(*axis).unwrap_or(0.0);
+            Vec2::X * if *inverted { -value } else { value }
+        }
+        (Binding::GamepadStick { x, y }, Some(gamepad)) => Vec2::new(
+            gamepad.get(*x).unwrap_or(0.0),
+            gamepad.get(*y).unwrap_or(0.0),
+        ),
+        _ => Vec2::ZERO,
+    }
+}
+
+fn update_action_state(
+    mut state: ResMut<ActionState>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<(Entity, &Gamepad)>,
+) {
+    let sources: Vec<InputSource> = std::iter::once(InputSource::KeyboardMouse)
+        .chain(
+            gamepads
+                .iter()
+                .map(|(entity, _)| InputSource::Gamepad(entity)),
+        )
+        .collect();
+    state.sources.retain(|source, _| sources.contains(source));
+
+    let state = state.as_mut();
+    for (action, binding) in &bindings.0 {
+        // every binding contributes, the sum is clamped to the unit square
+        let mut any = Vec2::ZERO;
+        for source in &sources {
+            let value = binding
+                .bindings
+                .iter()
+                .map(|input| {
+                    binding.shape(binding_value(input, *source, &keyboard, &mouse, &gamepads))
+                })
+                .sum::<Vec2>()
+                .clamp(-Vec2::ONE, Vec2::ONE);
+            state
+                .sources
+                .entry(*source)
+                .or_default()
+                .update(*action, value);
+            any += value;
+        }
+        state.any.update(*action, any.clamp(-Vec2::ONE, Vec2::ONE));
+    }
+}
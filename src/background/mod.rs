@@ -1,7 +1,9 @@
 mod twister;
 
+use crate::collision::Collider;
 use crate::global_state::GlobalState;
 use crate::material::parallax_material;
+use crate::nav::NavGround;
 
 use bevy::color::palettes::tailwind::*;
 use bevy::prelude::*;
@@ -97,13 +99,16 @@ fn populate_background(
         BackgroundMarker,
         Mesh3d(meshes.add(Cuboid::new(1.0, 5.0, 1.0))),
         MeshMaterial3d(debug_material),
+        Collider::cuboid(Vec3::new(1.0, 5.0, 1.0)),
         Transform::from_xyz(0.0, 2.5, -10.0),
     ));
 
     // cube
+    let cube_mesh = make_cube_mesh();
     commands.spawn((
         BackgroundMarker,
-        Mesh3d(meshes.add(make_cube_mesh())),
+        Collider::convex_hull(&cube_mesh).unwrap(),
+        Mesh3d(meshes.add(cube_mesh)),
         MeshMaterial3d(materials.add(StandardMaterial {
             base_color_texture: Some(asset_server.load("textures/array_texture.png")),
             ..default()
@@ -123,11 +128,13 @@ fn populate_background(
             ),
         ),
         MeshMaterial3d(parallal_material),
+        Collider::cuboid(Vec3::ONE),
         Transform::from_xyz(3.0, 2.0, 18.0).with_scale(Vec3::ONE * 4.0),
     ));
 
     // plain plane
     commands.spawn((
+        NavGround,
         Mesh3d(
             meshes.add(
                 Plane3d::default()
@@ -1,4 +1,5 @@
 use super::BackgroundMarker;
+use crate::collision::Collider;
 
 use bevy::prelude::*;
 use bevy::render::{
@@ -42,6 +43,16 @@ pub fn populate(
 
     let joint_entities = vec![joint_0, joint_1];
 
+    // lower edge of the ribbon, the part swaying within reach of the spiders, from x 10 to 20
+    commands.spawn((
+        BackgroundMarker,
+        Collider::Capsule {
+            radius: 1.0,
+            half_length: 5.0,
+        },
+        Transform::from_xyz(15.0, 1.0, -10.0).with_rotation(Quat::from_rotation_z(PI / 2.0)),
+    ));
+
     // Create inverse bindpose matrices for a skeleton consists of 2 joints
     // let center = Vec3::new(-0.5, -1.0, 0.0);
     let inverse_bindposes = inverse_bindposes_assets.add(vec![
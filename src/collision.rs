@@ -0,0 +1,265 @@
+use crate::spatial::{Indexed, SpatialIndex};
+
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+
+const BROADPHASE_MARGIN: f32 = 10.0; // m, larger than any collider extent from its origin
+
+/// Solid shape of a scene entity, expressed in its local frame
+#[derive(Component, Clone, Debug)]
+#[require(Indexed)]
+pub enum Collider {
+    Cuboid { half_size: Vec3 },
+    Capsule { radius: f32, half_length: f32 }, // along local Y, a sphere without half length
+    ConvexHull { points: Vec<Vec3> },
+}
+
+/// Vertical cylinder blocking a vehicle body, standing on the body position
+#[derive(Component, Clone, Debug)]
+pub struct Footprint {
+    pub radius: f32, // m
+    pub height: f32, // m
+}
+
+impl Default for Footprint {
+    fn default() -> Self {
+        Self {
+            radius: 2.5,
+            height: 3.0,
+        }
+    }
+}
+
+impl Collider {
+    pub fn cuboid(size: Vec3) -> Self {
+        Self::Cuboid {
+            half_size: size / 2.0,
+        }
+    }
+
+    /// Hull of the mesh vertices, none if the mesh has no positions
+    pub fn convex_hull(mesh: &Mesh) -> Option<Self> {
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return None;
+        };
+        if positions.is_empty() {
+            return None;
+        }
+        let points = positions.iter().map(|point| Vec3::from(*point)).collect();
+        Some(Self::ConvexHull { points })
+    }
+
+    /// Shadow of the collider on the ground plane, along with its vertical extent
+    pub fn footprint(&self, transform: &GlobalTransform) -> Obstacle {
+        let transform = transform.affine();
+        let corners = |points: &[Vec3]| -> Vec<Vec3> {
+            points
+                .iter()
+                .map(|point| transform.transform_point3(*point))
+                .collect()
+        };
+        match self {
+            Collider::Capsule {
+                radius,
+                half_length,
+            } => {
+                let aa = transform.transform_point3(Vec3::Y * *half_length);
+                let bb = transform.transform_point3(-Vec3::Y * *half_length);
+                let radius = radius * transform.matrix3.x_axis.length();
+                Obstacle {
+                    shape: Shape::Stadium(aa.xz(), bb.xz(), radius),
+                    bottom: aa.y.min(bb.y) - radius,
+                    top: aa.y.max(bb.y) + radius,
+                }
+            }
+            Collider::Cuboid { half_size } => {
+                let mut points = Vec::new();
+                for sx in [-1.0, 1.0] {
+                    for sy in [-1.0, 1.0] {
+                        for sz in [-1.0, 1.0] {
+                            points.push(*half_size * Vec3::new(sx, sy, sz));
+                        }
+                    }
+                }
+                Obstacle::from_points(&corners(&points))
+            }
+            Collider::ConvexHull { points } => Obstacle::from_points(&corners(points)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Stadium(Vec2, Vec2, f32), // segment swept by a disk
+    Polygon(Vec<Vec2>),       // convex, counter clockwise in the xz plane
+}
+
+/// World space collider projected on the ground plane
+#[derive(Clone, Debug)]
+pub struct Obstacle {
+    pub shape: Shape,
+    pub bottom: f32, // m
+    pub top: f32,    // m
+}
+
+impl Obstacle {
+    fn from_points(points: &[Vec3]) -> Self {
+        let bottom = points.iter().map(|point| point.y).fold(f32::MAX, f32::min);
+        let top = points.iter().map(|point| point.y).fold(f32::MIN, f32::max);
+        let points: Vec<Vec2> = points.iter().map(|point| point.xz()).collect();
+        Self {
+            shape: Shape::Polygon(convex_hull_2d(points)),
+            bottom,
+            top,
+        }
+    }
+
+    /// Bounding rectangle in the xz plane
+    pub fn bounds(&self) -> Rect {
+        match &self.shape {
+            Shape::Stadium(aa, bb, radius) => Rect::from_corners(*aa, *bb).inflate(*radius),
+            Shape::Polygon(points) => points
+                .iter()
+                .fold(Rect::EMPTY, |rect, point| rect.union_point(*point)),
+        }
+    }
+
+    /// Smallest displacement moving a disk out of the obstacle, along with the contact normal
+    pub fn push_out(&self, center: Vec2, radius: f32) -> Option<(Vec2, Vec2)> {
+        match &self.shape {
+            Shape::Stadium(aa, bb, obstacle_radius) => {
+                let closest = closest_point_on_segment(*aa, *bb, center);
+                push_from_point(center, radius + obstacle_radius, closest)
+            }
+            Shape::Polygon(points) if points.len() >= 3 => {
+                // signed distance to the farthest edge line, negative when inside
+                let mut best: Option<(f32, Vec2)> = None;
+                for (aa, bb) in edges(points) {
+                    let normal = -(bb - aa).perp().normalize_or_zero();
+                    let distance = (center - aa).dot(normal);
+                    if best.is_none_or(|(best_distance, _)| distance > best_distance) {
+                        best = Some((distance, normal));
+                    }
+                }
+                let (distance, normal) = best?;
+                if distance < 0.0 {
+                    return Some((normal * (radius - distance), normal));
+                }
+                let closest = edges(points)
+                    .map(|(aa, bb)| closest_point_on_segment(aa, bb, center))
+                    .min_by(|aa, bb| {
+                        aa.distance_squared(center)
+                            .total_cmp(&bb.distance_squared(center))
+                    })?;
+                push_from_point(center, radius, closest)
+            }
+            Shape::Polygon(_) => None,
+        }
+    }
+}
+
+fn edges(points: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(aa, bb)| (*aa, *bb))
+}
+
+fn closest_point_on_segment(aa: Vec2, bb: Vec2, point: Vec2) -> Vec2 {
+    let delta = bb - aa;
+    let length_squared = delta.length_squared();
+    if length_squared <= 0.0 {
+        return aa;
+    }
+    let alpha = ((point - aa).dot(delta) / length_squared).clamp(0.0, 1.0);
+    aa + delta * alpha
+}
+
+fn push_from_point(center: Vec2, radius: f32, closest: Vec2) -> Option<(Vec2, Vec2)> {
+    let delta = center - closest;
+    let distance = delta.length();
+    if distance >= radius {
+        return None;
+    }
+    let normal = delta.try_normalize().unwrap_or(Vec2::X);
+    Some((normal * (radius - distance), normal))
+}
+
+/// Andrew's monotone chain, returns the hull counter clockwise
+fn convex_hull_2d(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|aa, bb| aa.x.total_cmp(&bb.x).then(aa.y.total_cmp(&bb.y)));
+    points.dedup_by(|aa, bb| aa.distance_squared(*bb) < 1e-8);
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |oo: Vec2, aa: Vec2, bb: Vec2| (aa - oo).perp_dot(bb - oo);
+    let mut hull: Vec<Vec2> = Vec::new();
+    for pass in 0..2 {
+        let start = hull.len();
+        let ordered: Box<dyn Iterator<Item = &Vec2>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for point in ordered {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], *point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Obstacles of the colliders indexed near a footprint, in a stable order
+pub fn gather(
+    index: &SpatialIndex,
+    colliders: &Query<(&Collider, &GlobalTransform)>,
+    center: Vec2,
+    footprint: &Footprint,
+    bottom: f32,
+) -> Vec<Obstacle> {
+    let position = Vec3::new(center.x, bottom + footprint.height / 2.0, center.y);
+    let mut entities = index.within_radius(position, footprint.radius + BROADPHASE_MARGIN);
+    entities.sort_unstable();
+    entities
+        .into_iter()
+        .filter_map(|entity| colliders.get(entity).ok())
+        .map(|(collider, transform)| collider.footprint(transform))
+        .collect()
+}
+
+/// Slides a disk along the obstacles it overlaps, returns the accumulated push and the last contact normal
+pub fn resolve(
+    obstacles: &[Obstacle],
+    center: Vec2,
+    footprint: &Footprint,
+    bottom: f32,
+) -> Option<(Vec2, Vec2)> {
+    const ITERATIONS: usize = 4;
+    let top = bottom + footprint.height;
+    let mut center_ = center;
+    let mut contact = None;
+    for _ in 0..ITERATIONS {
+        let mut is_resolved = true;
+        for obstacle in obstacles {
+            if obstacle.top < bottom || obstacle.bottom > top {
+                continue;
+            }
+            if let Some((push, normal)) = obstacle.push_out(center_, footprint.radius) {
+                center_ += push;
+                contact = Some(normal);
+                is_resolved = false;
+            }
+        }
+        if is_resolved {
+            break;
+        }
+    }
+    contact.map(|normal| (center_ - center, normal))
+}
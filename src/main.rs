@@ -1,9 +1,14 @@
 //! spider ftw
 
+mod actions;
 mod background;
+mod collision;
 mod global_state;
 mod material;
+mod nav;
+mod players;
 mod simu;
+mod spatial;
 mod spider;
 mod ui;
 
@@ -21,11 +26,16 @@ fn main() {
         }),
         ..default()
     }));
+    app.add_plugins(actions::ActionsPlugin);
     app.add_plugins(background::BackgroundPlugin);
     app.add_plugins(global_state::GlobalStatePlugin);
     app.add_plugins(material::CustomMaterialPlugin);
+    app.add_plugins(nav::NavPlugin::default());
+    app.add_plugins(players::PlayersPlugin);
     app.add_plugins(simu::SimuPlugin);
-    app.add_plugins(spider::SpiderPlugin);
+    app.add_plugins(spatial::SpatialIndexPlugin);
+    app.add_plugins(spider::SpiderPlugin::default());
+    app.add_plugins(spider::FootstepPlugin);
     app.add_plugins(ui::UiPlugin);
 
     /*
@@ -87,7 +97,4 @@ fn keyboard_shortcuts(
     if can_quit && keyboard.just_pressed(KeyCode::Escape) {
         writer.write(AppExit::Success);
     }
-    if keyboard.just_pressed(KeyCode::KeyT) {
-        warn!("reseed");
-    }
 }
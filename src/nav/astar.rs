@@ -0,0 +1,117 @@
+use super::grid::{Cell, OccupancyGrid};
+
+use bevy::prelude::*;
+use priority_queue::PriorityQueue;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+const COST_STRAIGHT: u32 = 10;
+const COST_DIAGONAL: u32 = 14;
+const COST_PENALTY: u32 = 2; // per penalty level of the entered cell
+const GOAL_SNAP_RINGS: usize = 8; // cells searched around a blocked goal
+
+/// Octile distance, admissible on an 8-connected grid
+fn heuristic(aa: Cell, bb: Cell) -> u32 {
+    let di = aa.0.abs_diff(bb.0) as u32;
+    let dj = aa.1.abs_diff(bb.1) as u32;
+    COST_STRAIGHT * di.max(dj) + (COST_DIAGONAL - COST_STRAIGHT) * di.min(dj)
+}
+
+fn neighbors(grid: &OccupancyGrid, cell: Cell) -> impl Iterator<Item = (Cell, u32)> + '_ {
+    const OFFSETS: [(isize, isize); 8] = [
+        (1, 0),
+        (-1, 0),
+        (0, 1),
+        (0, -1),
+        (1, 1),
+        (1, -1),
+        (-1, 1),
+        (-1, -1),
+    ];
+    OFFSETS.iter().filter_map(move |(di, dj)| {
+        let ii = cell.0.checked_add_signed(*di)?;
+        let jj = cell.1.checked_add_signed(*dj)?;
+        if ii >= grid.width || jj >= grid.height || grid.is_blocked((ii, jj)) {
+            return None;
+        }
+        if *di != 0 && *dj != 0 {
+            // no corner cutting
+            if grid.is_blocked((ii, cell.1)) || grid.is_blocked((cell.0, jj)) {
+                return None;
+            }
+            return Some(((ii, jj), COST_DIAGONAL));
+        }
+        Some(((ii, jj), COST_STRAIGHT))
+    })
+}
+
+/// Cell path from start to goal, the start cell may be blocked, a blocked goal is moved to the closest free cell
+pub fn find_path(grid: &OccupancyGrid, start: Vec2, goal: Vec2) -> Option<Vec<Cell>> {
+    let start = grid.cell(start)?;
+    let goal = grid.nearest_free(grid.cell(goal)?, GOAL_SNAP_RINGS)?;
+
+    let mut queue: PriorityQueue<Cell, Reverse<u32>> = PriorityQueue::new();
+    let mut costs: HashMap<Cell, u32> = HashMap::new();
+    let mut parents: HashMap<Cell, Cell> = HashMap::new();
+    queue.push(start, Reverse(heuristic(start, goal)));
+    costs.insert(start, 0);
+
+    while let Some((cell, _)) = queue.pop() {
+        if cell == goal {
+            let mut path = vec![cell];
+            let mut cell = cell;
+            while let Some(parent) = parents.get(&cell) {
+                cell = *parent;
+                path.push(cell);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        let cost = costs[&cell];
+        for (next, step) in neighbors(grid, cell) {
+            let next_cost = cost + step + COST_PENALTY * grid.cost(next) as u32;
+            if costs.get(&next).is_some_and(|known| *known <= next_cost) {
+                continue;
+            }
+            costs.insert(next, next_cost);
+            parents.insert(next, cell);
+            queue.push_increase(next, Reverse(next_cost + heuristic(next, goal)));
+        }
+    }
+
+    None
+}
+
+/// Drops the waypoints that can be skipped in a straight line
+pub fn smooth_path(grid: &OccupancyGrid, start: Vec2, cells: &[Cell]) -> Vec<Vec2> {
+    let points: Vec<Vec2> = cells.iter().map(|cell| grid.center(*cell)).collect();
+    let mut waypoints = Vec::new();
+    let mut anchor = start;
+    let mut index = 0;
+    while index < points.len() {
+        let mut farthest = index;
+        for candidate in index + 1..points.len() {
+            if grid.is_line_free(anchor, points[candidate]) {
+                farthest = candidate;
+            }
+        }
+        anchor = points[farthest];
+        waypoints.push(anchor);
+        index = farthest + 1;
+    }
+    waypoints
+}
+
+/// Smoothed waypoints from start to goal, the start position is not included
+pub fn plan(grid: &OccupancyGrid, start: Vec2, goal: Vec2) -> Option<Vec<Vec2>> {
+    let cells = find_path(grid, start, goal)?;
+    let mut waypoints = smooth_path(grid, start, &cells);
+    // finish on the exact goal when it is reachable from the last cell
+    if let Some(last) = waypoints.last_mut() {
+        if grid.is_line_free(*last, goal) {
+            *last = goal;
+        }
+    }
+    Some(waypoints)
+}
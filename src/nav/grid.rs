@@ -0,0 +1,184 @@
+use crate::collision::Obstacle;
+
+use bevy::prelude::*;
+
+use serde::Serialize;
+
+pub type Cell = (usize, usize);
+
+pub const BLOCKED: u8 = u8::MAX;
+pub const MAX_PENALTY: u8 = 9; // extra cost of the cells hugging obstacles
+
+/// Traversal cost of the ground plane for an agent of the given radius
+#[derive(Clone, Debug)]
+pub struct OccupancyGrid {
+    pub origin: Vec2,      // m, corner of the first cell in the xz plane
+    pub cell_size: f32,    // m
+    pub width: usize,      // cells along x
+    pub height: usize,     // cells along z
+    pub agent_radius: f32, // m
+    pub margin: f32,       // m, clearance beyond the agent radius where cells get penalized
+    pub costs: Vec<u8>,    // 0 is free, BLOCKED can not be entered
+}
+
+impl OccupancyGrid {
+    pub fn new(bounds: Rect, cell_size: f32, agent_radius: f32, margin: f32) -> Self {
+        let width = (bounds.width() / cell_size).ceil().max(1.0) as usize;
+        let height = (bounds.height() / cell_size).ceil().max(1.0) as usize;
+        Self {
+            origin: bounds.min,
+            cell_size,
+            width,
+            height,
+            agent_radius,
+            margin,
+            costs: vec![0; width * height],
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        let size = Vec2::new(self.width as f32, self.height as f32) * self.cell_size;
+        Rect::from_corners(self.origin, self.origin + size)
+    }
+
+    /// Recomputes the cells overlapping region, the whole grid when none
+    pub fn bake(&mut self, obstacles: &[Obstacle], region: Option<Rect>) {
+        let region = region.unwrap_or_else(|| self.bounds());
+        let region = region.intersect(self.bounds());
+        if region.is_empty() {
+            return;
+        }
+        let (min, max) = (self.clamped_cell(region.min), self.clamped_cell(region.max));
+        let radius = self.agent_radius + self.margin;
+        for jj in min.1..=max.1 {
+            for ii in min.0..=max.0 {
+                let center = self.center((ii, jj));
+                let penetration = obstacles
+                    .iter()
+                    .filter_map(|obstacle| obstacle.push_out(center, radius))
+                    .map(|(push, _)| push.length())
+                    .fold(0.0, f32::max);
+                self.costs[jj * self.width + ii] = if penetration > self.margin {
+                    BLOCKED
+                } else {
+                    (penetration / self.margin * MAX_PENALTY as f32).ceil() as u8
+                };
+            }
+        }
+    }
+
+    fn clamped_cell(&self, pos: Vec2) -> Cell {
+        let local = ((pos - self.origin) / self.cell_size).max(Vec2::ZERO);
+        (
+            (local.x as usize).min(self.width - 1),
+            (local.y as usize).min(self.height - 1),
+        )
+    }
+
+    pub fn cell(&self, pos: Vec2) -> Option<Cell> {
+        let local = (pos - self.origin) / self.cell_size;
+        if local.x < 0.0 || local.y < 0.0 {
+            return None;
+        }
+        let cell = (local.x as usize, local.y as usize);
+        (cell.0 < self.width && cell.1 < self.height).then_some(cell)
+    }
+
+    pub fn center(&self, cell: Cell) -> Vec2 {
+        self.origin + (Vec2::new(cell.0 as f32, cell.1 as f32) + 0.5) * self.cell_size
+    }
+
+    pub fn cost(&self, cell: Cell) -> u8 {
+        self.costs[cell.1 * self.width + cell.0]
+    }
+
+    pub fn is_blocked(&self, cell: Cell) -> bool {
+        self.cost(cell) == BLOCKED
+    }
+
+    /// Closest free cell in growing square rings, up to max_rings away
+    pub fn nearest_free(&self, cell: Cell, max_rings: usize) -> Option<Cell> {
+        for ring in 0..=max_rings {
+            let ring = ring as isize;
+            let mut best: Option<(isize, Cell)> = None;
+            for dj in -ring..=ring {
+                for di in -ring..=ring {
+                    if di.abs() != ring && dj.abs() != ring {
+                        continue;
+                    }
+                    let ii = cell.0 as isize + di;
+                    let jj = cell.1 as isize + dj;
+                    if ii < 0 || jj < 0 || ii >= self.width as isize || jj >= self.height as isize {
+                        continue;
+                    }
+                    let candidate = (ii as usize, jj as usize);
+                    let distance = di * di + dj * dj;
+                    if !self.is_blocked(candidate)
+                        && best.is_none_or(|(best_distance, _)| distance < best_distance)
+                    {
+                        best = Some((distance, candidate));
+                    }
+                }
+            }
+            if let Some((_, candidate)) = best {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+
+    /// True when the straight segment only crosses free cells
+    pub fn is_line_free(&self, aa: Vec2, bb: Vec2) -> bool {
+        let steps = ((bb - aa).length() / (self.cell_size / 2.0)).ceil() as usize;
+        (0..=steps).all(|step| {
+            let alpha = if steps == 0 {
+                0.0
+            } else {
+                step as f32 / steps as f32
+            };
+            match self.cell(aa.lerp(bb, alpha)) {
+                Some(cell) => !self.is_blocked(cell),
+                None => false,
+            }
+        })
+    }
+}
+
+//////////////////////////////////////////////////////////////////////
+
+/// On disk layout, one string per row of cells,
+/// '.' is free, '1' to '9' are penalized and '#' is blocked
+#[derive(Serialize)]
+struct OccupancyGridFile {
+    origin: Vec2,
+    cell_size: f32,
+    agent_radius: f32,
+    margin: f32,
+    rows: Vec<String>,
+}
+
+impl OccupancyGrid {
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        let rows = self
+            .costs
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|cost| match *cost {
+                        0 => '.',
+                        BLOCKED => '#',
+                        cost => char::from_digit(cost.min(MAX_PENALTY) as u32, 10).unwrap(),
+                    })
+                    .collect()
+            })
+            .collect();
+        let file = OccupancyGridFile {
+            origin: self.origin,
+            cell_size: self.cell_size,
+            agent_radius: self.agent_radius,
+            margin: self.margin,
+            rows,
+        };
+        ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+    }
+}
@@ -0,0 +1,271 @@
+mod astar;
+mod grid;
+
+#[cfg(not(target_family = "wasm"))]
+use crate::actions::{Action, ActionState};
+use crate::collision::{Collider, Footprint, Obstacle};
+use crate::global_state::GlobalState;
+use crate::ui::UiState;
+
+use bevy::color::palettes::css::*;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use grid::{BLOCKED, MAX_PENALTY, OccupancyGrid};
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+pub use astar::plan;
+
+#[cfg(not(target_family = "wasm"))]
+const GRID_DUMP_PATH: &str = "nav_grid.ron";
+
+//////////////////////////////////////////////////////////////////////
+
+/// Bakes the ground occupancy used by the planners
+pub struct NavPlugin {
+    pub cell_size: f32,    // m
+    pub agent_radius: f32, // m, obstacles are inflated by this radius
+    pub agent_height: f32, // m, obstacles above are ignored
+    pub margin: f32,       // m, cells closer than this to the inflated obstacles are penalized
+}
+
+impl Default for NavPlugin {
+    fn default() -> Self {
+        let agent = Footprint::default();
+        Self {
+            cell_size: 0.5,
+            agent_radius: agent.radius,
+            agent_height: agent.height,
+            margin: 1.0,
+        }
+    }
+}
+
+impl Plugin for NavPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NavBakeSettings {
+            cell_size: self.cell_size,
+            agent_radius: self.agent_radius,
+            agent_height: self.agent_height,
+            margin: self.margin,
+        });
+        app.init_resource::<NavGrid>();
+        app.add_systems(
+            PostUpdate,
+            bake_nav_grid
+                .after(TransformSystem::TransformPropagate)
+                .run_if(in_state(GlobalState::Ready)),
+        );
+        app.add_systems(
+            Update,
+            display_nav_grid.run_if(in_state(GlobalState::Ready)),
+        );
+        #[cfg(not(target_family = "wasm"))]
+        app.add_systems(Update, dump_nav_grid.run_if(in_state(GlobalState::Ready)));
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct NavBakeSettings {
+    pub cell_size: f32,
+    pub agent_radius: f32,
+    pub agent_height: f32,
+    pub margin: f32,
+}
+
+/// Tags the meshes the agents walk on, the grid covers their union
+#[derive(Component, Default)]
+pub struct NavGround;
+
+/// Occupancy of the ground, none until some ground is spawned
+#[derive(Resource, Default)]
+pub struct NavGrid {
+    pub grid: Option<OccupancyGrid>,
+}
+
+/// Footprints used by the last bake, to find out which regions are dirty
+#[derive(Default)]
+struct BakedObstacles(HashMap<Entity, Obstacle>);
+
+fn ground_bounds(grounds: &Query<(&Aabb, &GlobalTransform), With<NavGround>>) -> Rect {
+    let mut bounds = Rect::EMPTY;
+    for (aabb, transform) in grounds {
+        let (center, half) = (Vec3::from(aabb.center), Vec3::from(aabb.half_extents));
+        for sx in [-1.0, 1.0] {
+            for sz in [-1.0, 1.0] {
+                let corner = transform.transform_point(center + half * Vec3::new(sx, 0.0, sz));
+                bounds = bounds.union_point(corner.xz());
+            }
+        }
+    }
+    bounds
+}
+
+/// Bakes the whole grid when the ground changes, only the dirty regions when obstacles move
+fn bake_nav_grid(
+    mut nav: ResMut<NavGrid>,
+    settings: Res<NavBakeSettings>,
+    mut baked: Local<BakedObstacles>,
+    grounds: Query<(&Aabb, &GlobalTransform), With<NavGround>>,
+    changed_grounds: Query<(), (With<NavGround>, Changed<GlobalTransform>)>,
+    colliders: Query<(Entity, &Collider, &GlobalTransform)>,
+    changed_colliders: Query<Entity, (With<Collider>, Changed<GlobalTransform>)>,
+    mut removed_colliders: RemovedComponents<Collider>,
+) {
+    // obstacles overlapping the agent height band occupy cells, the others are walked under or over
+    let is_blocking =
+        |obstacle: &Obstacle| obstacle.bottom < settings.agent_height && obstacle.top > 0.0;
+    let inflation = settings.agent_radius + settings.margin + settings.cell_size;
+
+    // union of the previous and current footprints of everything that moved
+    let mut dirty = Rect::EMPTY;
+    for entity in removed_colliders.read() {
+        if let Some(obstacle) = baked.0.remove(&entity) {
+            dirty = dirty.union(obstacle.bounds());
+        }
+    }
+    for entity in &changed_colliders {
+        if let Some(obstacle) = baked.0.remove(&entity) {
+            dirty = dirty.union(obstacle.bounds());
+        }
+        let (_, collider, transform) = colliders.get(entity).unwrap();
+        let obstacle = collider.footprint(transform);
+        if is_blocking(&obstacle) {
+            dirty = dirty.union(obstacle.bounds());
+            baked.0.insert(entity, obstacle);
+        }
+    }
+
+    let obstacles: Vec<Obstacle> = baked.0.values().cloned().collect();
+    if nav.grid.is_none() || !changed_grounds.is_empty() {
+        let bounds = ground_bounds(&grounds);
+        if bounds.is_empty() {
+            return;
+        }
+        let mut grid = OccupancyGrid::new(
+            bounds,
+            settings.cell_size,
+            settings.agent_radius,
+            settings.margin,
+        );
+        grid.bake(&obstacles, None);
+        info!(
+            "** baked nav grid {}x{} from {} obstacles **",
+            grid.width,
+            grid.height,
+            obstacles.len()
+        );
+        nav.grid = Some(grid);
+        return;
+    }
+
+    if dirty.is_empty() {
+        return;
+    }
+    let dirty = dirty.inflate(inflation);
+    if let Some(grid) = nav.grid.as_mut() {
+        grid.bake(&obstacles, Some(dirty));
+        debug!("rebaked nav grid region {:?}", dirty);
+    }
+}
+
+fn display_nav_grid(ui_state: Res<UiState>, nav: Res<NavGrid>, mut gizmos: Gizmos) {
+    if !ui_state.display_gizmos {
+        return;
+    }
+    let Some(grid) = &nav.grid else {
+        return;
+    };
+    let flat = Quat::from_rotation_x(PI / 2.0);
+    let lift = |pos: Vec2| Vec3::new(pos.x, 0.05, pos.y);
+    let bounds = grid.bounds();
+    gizmos.rect(
+        Isometry3d::new(lift(bounds.center()), flat),
+        bounds.size(),
+        WHITE,
+    );
+    for jj in 0..grid.height {
+        for ii in 0..grid.width {
+            let cost = grid.cost((ii, jj));
+            if cost == 0 {
+                continue;
+            }
+            let color = if cost == BLOCKED {
+                Color::from(RED)
+            } else {
+                Color::from(ORANGE).with_alpha(cost as f32 / MAX_PENALTY as f32)
+            };
+            gizmos.rect(
+                Isometry3d::new(lift(grid.center((ii, jj))), flat),
+                Vec2::splat(grid.cell_size * 0.8),
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn dump_nav_grid(nav: Res<NavGrid>, actions: Res<ActionState>) {
+    if !actions.any.just_pressed(Action::DumpNavGrid) {
+        return;
+    }
+    let Some(grid) = &nav.grid else {
+        warn!("no nav grid to dump");
+        return;
+    };
+    let result = grid
+        .to_ron()
+        .map_err(|err| err.to_string())
+        .and_then(|text| std::fs::write(GRID_DUMP_PATH, text).map_err(|err| err.to_string()));
+    match result {
+        Ok(()) => info!("nav grid dumped to {}", GRID_DUMP_PATH),
+        Err(err) => error!("could not dump nav grid: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn bake_blocks_cells_under_a_cube() {
+        let mut world = World::new();
+        world.insert_resource(NavBakeSettings {
+            cell_size: 0.5,
+            agent_radius: 1.0,
+            agent_height: 3.0,
+            margin: 0.5,
+        });
+        world.init_resource::<NavGrid>();
+        world.spawn((
+            NavGround,
+            Aabb::from_min_max(Vec3::new(-10.0, 0.0, -10.0), Vec3::new(10.0, 0.0, 10.0)),
+            GlobalTransform::IDENTITY,
+        ));
+        world.spawn((
+            Collider::cuboid(Vec3::splat(2.0)),
+            GlobalTransform::from_translation(Vec3::Y),
+        ));
+        world.run_system_once(bake_nav_grid).unwrap();
+
+        let nav = world.resource::<NavGrid>();
+        let grid = nav.grid.as_ref().unwrap();
+        let cost = |pos: Vec2| grid.cost(grid.cell(pos).unwrap());
+        for pos in [Vec2::ZERO, Vec2::new(0.75, -0.75), Vec2::new(-0.75, 0.75)] {
+            assert_eq!(cost(pos), BLOCKED, "{} should be blocked", pos);
+        }
+        // further than the agent radius and the margin from the cube faces
+        for pos in [
+            Vec2::new(3.25, 0.25),
+            Vec2::new(-3.25, 0.25),
+            Vec2::new(0.25, 3.25),
+            Vec2::new(0.25, -3.25),
+            Vec2::new(8.0, 8.0),
+        ] {
+            assert_eq!(cost(pos), 0, "{} should be free", pos);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::actions::InputSource;
+
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+
+const PLAYER_SLOTS: usize = 2;
+const JOIN_BUTTON: GamepadButton = GamepadButton::Start;
+
+/// Input source driving each player slot, the keyboard owns the first slot
+#[derive(Resource, Debug)]
+pub struct PlayerRegistry {
+    slots: Vec<Option<InputSource>>,
+}
+
+impl Default for PlayerRegistry {
+    fn default() -> Self {
+        let mut slots = vec![None; PLAYER_SLOTS];
+        slots[0] = Some(InputSource::KeyboardMouse);
+        Self { slots }
+    }
+}
+
+impl PlayerRegistry {
+    pub fn source(&self, slot: usize) -> Option<InputSource> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    /// Assigns source to the first free slot
+    fn join(&mut self, source: InputSource) -> Option<usize> {
+        if self.slots.contains(&Some(source)) {
+            return None;
+        }
+        let slot = self.slots.iter().position(Option::is_none)?;
+        self.slots[slot] = Some(source);
+        Some(slot)
+    }
+
+    /// Frees the slot driven by source
+    fn leave(&mut self, source: InputSource) -> Option<usize> {
+        let slot = self.slots.iter().position(|other| *other == Some(source))?;
+        self.slots[slot] = None;
+        Some(slot)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////
+
+pub struct PlayersPlugin;
+
+impl Plugin for PlayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerRegistry>();
+        app.add_systems(
+            PreUpdate,
+            (handle_gamepad_connections, join_players)
+                .chain()
+                .after(bevy::input::InputSystem),
+        );
+    }
+}
+
+fn handle_gamepad_connections(
+    mut registry: ResMut<PlayerRegistry>,
+    mut events: EventReader<GamepadConnectionEvent>,
+) {
+    for event in events.read() {
+        let source = InputSource::Gamepad(event.gamepad);
+        match &event.connection {
+            GamepadConnection::Connected { name, .. } => {
+                info!("gamepad {} connected, press start to join", name);
+            }
+            GamepadConnection::Disconnected => {
+                if let Some(slot) = registry.leave(source) {
+                    info!("player {} left, gamepad disconnected", slot + 1);
+                }
+            }
+        }
+    }
+}
+
+fn join_players(mut registry: ResMut<PlayerRegistry>, gamepads: Query<(Entity, &Gamepad)>) {
+    for (entity, gamepad) in &gamepads {
+        if !gamepad.just_pressed(JOIN_BUTTON) {
+            continue;
+        }
+        match registry.join(InputSource::Gamepad(entity)) {
+            Some(slot) => info!("player {} joined with gamepad {:?}", slot + 1, entity),
+            None => debug!("gamepad {:?} has no free slot to join", entity),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use super::{world_to_texel, SimuSettings, SimuTriggers, SIMU_PLANE_SIZE, TEXTURE_SIZE};
+
+use bevy::prelude::*;
+use bevy::render::render_resource::ShaderType;
+
+pub const MAX_STAMPS: usize = 16; // per frame
+const BRUSH_HEIGHT: f32 = 2.0; // m, brushes further from the plane do not paint
+
+/// Cells set by a brush within its radius
+#[derive(Clone, Copy, Debug)]
+pub enum BrushPattern {
+    Solid,
+    Random { density: f32 }, // fraction of live cells in [0, 1]
+    Checker,
+}
+
+/// Stamps live cells into the simulation along the path of its entity
+#[derive(Component, Clone, Debug)]
+pub struct SimuBrush {
+    pub radius: f32,    // m
+    pub channel: usize, // 0 red, 1 green, 2 blue
+    pub pattern: BrushPattern,
+    previous: Option<Vec2>, // texel of the last stamp, joined to the next one
+}
+
+impl Default for SimuBrush {
+    fn default() -> Self {
+        Self::new(1.5, 0, BrushPattern::Random { density: 0.5 })
+    }
+}
+
+impl SimuBrush {
+    pub fn new(radius: f32, channel: usize, pattern: BrushPattern) -> Self {
+        Self {
+            radius,
+            channel,
+            pattern,
+            previous: None,
+        }
+    }
+}
+
+/// Segment of live cells, in texels, painted before the next step
+#[derive(ShaderType, Clone, Copy, Default, Debug)]
+pub struct StampUniform {
+    point_a: Vec2,
+    point_b: Vec2,
+    radius: f32,
+    channel: u32,
+    pattern: u32, // 0 solid, 1 random, 2 checker
+    density: f32,
+}
+
+/// Maps every brush over the plane to texels, stamps once the brush moved by a texel
+pub fn paint_simu_brushes(
+    mut brushes: Query<(&mut SimuBrush, &GlobalTransform)>,
+    mut planes: Query<(&mut SimuSettings, &GlobalTransform)>,
+    mut simu_triggers: ResMut<SimuTriggers>,
+    mut frame: Local<u32>,
+) {
+    *frame = frame.wrapping_add(1);
+    let texture_size = Vec2::new(TEXTURE_SIZE.0 as f32, TEXTURE_SIZE.1 as f32);
+    let texels_per_meter = texture_size / SIMU_PLANE_SIZE;
+
+    let mut should_paint = false;
+    for (mut settings, plane_transform) in &mut planes {
+        let mut stamp_count = 0;
+        for (mut brush, transform) in &mut brushes {
+            let Some(texel) =
+                world_to_texel(plane_transform, transform.translation(), BRUSH_HEIGHT)
+            else {
+                brush.previous = None;
+                continue;
+            };
+            let previous = brush.previous.unwrap_or(texel);
+            let is_moved = brush
+                .previous
+                .is_none_or(|previous| previous.distance(texel) >= 1.0);
+            if !is_moved {
+                continue;
+            }
+            if stamp_count >= MAX_STAMPS {
+                debug!("too many brushes, stamp dropped");
+                continue;
+            }
+            let (pattern, density) = match brush.pattern {
+                BrushPattern::Solid => (0, 1.0),
+                BrushPattern::Random { density } => (1, density),
+                BrushPattern::Checker => (2, 1.0),
+            };
+            settings.stamps[stamp_count] = StampUniform {
+                point_a: previous,
+                point_b: texel,
+                radius: brush.radius * texels_per_meter.x.min(texels_per_meter.y),
+                channel: brush.channel.min(2) as u32,
+                pattern,
+                density,
+            };
+            stamp_count += 1;
+            brush.previous = Some(texel);
+        }
+        settings.stamp_count = stamp_count as u32;
+        settings.stamp_seed = *frame;
+        should_paint |= stamp_count > 0;
+    }
+    simu_triggers.should_paint = should_paint;
+}
@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResource;
+
+use crate::actions::{Action, ActionState};
+use crate::ui::UiState;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+const MAX_STEPS_PER_FRAME: u32 = 16;
+
+/// Speeds selectable from the ui, the first one is the default
+pub const SIMU_SPEEDS: &[(&str, f32)] = &[
+    ("60 sps", 60.0),
+    ("1 sps", 1.0),
+    ("5 sps", 5.0),
+    ("15 sps", 15.0),
+    ("30 sps", 30.0),
+    ("120 sps", 120.0),
+    ("240 sps", 240.0),
+    ("960 sps", 960.0),
+];
+
+/// Pace of the automaton, independent of the frame rate
+#[derive(Resource, Clone, ExtractResource)]
+pub struct SimuClock {
+    pub paused: bool,
+    pub speed_index: usize, // into SIMU_SPEEDS
+    pub steps_per_second: f32,
+    pending_steps: u32, // single steps requested while paused
+    accumulator: f32,   // fraction of a step carried over to the next frame
+    steps: u32,         // dispatched during the current frame
+}
+
+impl Default for SimuClock {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            speed_index: 0,
+            steps_per_second: SIMU_SPEEDS[0].1,
+            pending_steps: 0,
+            accumulator: 0.0,
+            steps: 0,
+        }
+    }
+}
+
+impl SimuClock {
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.accumulator = 0.0;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Advances a single generation, pausing the clock first
+    pub fn step(&mut self) {
+        self.pause();
+        self.pending_steps += 1;
+    }
+
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    fn select_speed(&mut self, speed_index: usize) {
+        self.speed_index = speed_index;
+        self.steps_per_second = SIMU_SPEEDS[speed_index].1;
+    }
+
+    /// Closest preset slower or faster than the current speed, none past the extremes
+    fn next_speed(&self, is_faster: bool) -> Option<usize> {
+        let current = self.steps_per_second;
+        SIMU_SPEEDS
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, speed))| match is_faster {
+                true => *speed > current,
+                false => *speed < current,
+            })
+            .min_by(|(_, (_, aa)), (_, (_, bb))| match is_faster {
+                true => aa.total_cmp(bb),
+                false => bb.total_cmp(aa),
+            })
+            .map(|(index, _)| index)
+    }
+}
+
+/// Generations computed since the last reseed, counted by the render world
+#[derive(Resource, Clone, Default)]
+pub struct SimuGeneration(Arc<AtomicU64>);
+
+impl SimuGeneration {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, steps: u32) {
+        self.0.fetch_add(steps as u64, Ordering::Relaxed);
+    }
+}
+
+/// Converts the elapsed time into whole steps for this frame
+pub fn advance_simu_clock(mut clock: ResMut<SimuClock>, time: Res<Time>) {
+    let clock = clock.as_mut();
+    let mut steps = std::mem::take(&mut clock.pending_steps);
+    if !clock.paused {
+        clock.accumulator += clock.steps_per_second * time.delta_secs();
+        let whole = clock.accumulator.floor();
+        clock.accumulator -= whole;
+        steps += whole as u32;
+    }
+    if steps > MAX_STEPS_PER_FRAME {
+        // drops the backlog instead of stalling the frame
+        clock.accumulator = 0.0;
+        steps = MAX_STEPS_PER_FRAME;
+    }
+    clock.steps = steps;
+}
+
+/// Pause, step, slower and faster actions drive the clock, the ui follows
+pub fn control_simu_clock(
+    mut clock: ResMut<SimuClock>,
+    mut ui_selection: Local<Option<(bool, usize)>>,
+    mut ui_state: ResMut<UiState>,
+    generation: Res<SimuGeneration>,
+    actions: Res<ActionState>,
+) {
+    // the ui only applies when its widgets change, so that shortcuts are not overridden
+    let mut is_updated = false;
+    let selection = (ui_state.simu_paused, ui_state.simu_speed_index);
+    if *ui_selection != Some(selection) {
+        if ui_selection.is_some_and(|(paused, _)| paused != selection.0) {
+            if selection.0 {
+                clock.pause();
+            } else {
+                clock.resume();
+            }
+        }
+        clock.select_speed(selection.1);
+        is_updated = ui_selection.is_some();
+        *ui_selection = Some(selection);
+    }
+
+    if actions.any.just_pressed(Action::PauseSimu) {
+        if clock.paused {
+            clock.resume();
+        } else {
+            clock.pause();
+        }
+        is_updated = true;
+    }
+    if actions.any.just_pressed(Action::StepSimu) {
+        clock.step();
+        is_updated = true;
+    }
+    if actions.any.just_pressed(Action::SlowerSimu) {
+        if let Some(speed_index) = clock.next_speed(false) {
+            clock.select_speed(speed_index);
+        }
+        is_updated = true;
+    }
+    if actions.any.just_pressed(Action::FasterSimu) {
+        if let Some(speed_index) = clock.next_speed(true) {
+            clock.select_speed(speed_index);
+        }
+        is_updated = true;
+    }
+
+    // shortcuts are written back so that the widgets follow the clock
+    let selection = (clock.paused, clock.speed_index);
+    if *ui_selection != Some(selection) {
+        (ui_state.simu_paused, ui_state.simu_speed_index) = selection;
+        *ui_selection = Some(selection);
+    }
+    ui_state.simu_generation = generation.get();
+
+    if is_updated {
+        info!(
+            "** simu {} at {} sps, generation {} **",
+            if clock.paused { "paused" } else { "running" },
+            clock.steps_per_second,
+            generation.get()
+        );
+    }
+}
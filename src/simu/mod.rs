@@ -0,0 +1,612 @@
+mod brush;
+mod clock;
+mod pattern;
+mod readback;
+mod rule;
+mod seed;
+
+use bevy::render::extract_component::{
+    ComponentUniforms, ExtractComponent, ExtractComponentPlugin, UniformComponentPlugin,
+};
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::graph::CameraDriverLabel;
+use bevy::render::render_asset::{RenderAssetUsages, RenderAssets};
+use bevy::render::render_graph::{Node, RenderGraph, RenderLabel};
+use bevy::render::render_resource::{
+    binding_types::{texture_storage_2d, uniform_buffer},
+    BindGroup, BindGroupEntries, BindGroupLayout, CachedComputePipelineId, ShaderType,
+    TextureFormat,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::texture::GpuImage;
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use crate::actions::{Action, ActionState};
+use crate::ui::{UiState, UiSystems};
+use brush::{StampUniform, MAX_STAMPS};
+use clock::{SimuClock, SimuGeneration};
+use pattern::{LifePattern, LifePatternLoader};
+use rule::{CellRule, RuleParseError, RuleUniform};
+
+use bevy::prelude::*;
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+pub use brush::{BrushPattern, SimuBrush};
+pub use clock::SIMU_SPEEDS;
+pub use readback::SimuReadback;
+pub use seed::{SimuReseed, SEED_PRESETS};
+
+const SHADER_PATH: &str = "shaders/simu.wgsl";
+const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba32Float;
+const TEXTURE_SIZE: (u32, u32) = (1024, 1024);
+const TEXEL_SIZE: usize = 16; // bytes, four f32 channels
+const WORKGROUP_SIZE: u32 = 8;
+const SIMU_PLANE_SIZE: f32 = 400.0; // m
+
+//////////////////////////////////////////////////////////////////////
+
+/// Rule presets selectable from the ui, one rule per red, green and blue channel
+pub const RULE_PRESETS: &[(&str, [&str; 3])] = &[
+    ("life", ["B3/S23", "B3/S23", "B3/S23"]),
+    ("highlife", ["B36/S23", "B36/S23", "B36/S23"]),
+    ("mixed", ["B3/S23", "B36/S23", "B3678/S34678"]),
+    ("brian's brain", ["B2/S/C3", "B2/S/C3", "B2/S/C3"]),
+    ("star wars", ["B2/S345/C4", "B2/S345/C4", "B2/S345/C4"]),
+    (
+        "bosco",
+        [
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R5,C0,M1,S34..58,B34..45,NM",
+        ],
+    ),
+];
+
+#[derive(Component, ShaderType, ExtractComponent, Clone)]
+struct SimuSettings {
+    rng_seed: u32,
+    stamp_count: u32,
+    stamp_seed: u32,
+    rules: [RuleUniform; 3],
+    stamps: [StampUniform; MAX_STAMPS],
+}
+
+impl Default for SimuSettings {
+    fn default() -> Self {
+        Self {
+            rng_seed: 42,
+            stamp_count: 0,
+            stamp_seed: 0,
+            rules: [RuleUniform::from(&CellRule::default()); 3],
+            stamps: [StampUniform::default(); MAX_STAMPS],
+        }
+    }
+}
+
+pub struct SimuPlugin;
+
+#[derive(Hash, Clone, Eq, PartialEq, Debug, RenderLabel)]
+enum SimuNodes {
+    Main,
+}
+
+impl Plugin for SimuPlugin {
+    fn build(&self, app: &mut App) {
+        info!("** build_simu **");
+
+        app.add_plugins((
+            // The settings will be a component that lives in the main world but will
+            // be extracted to the render world every frame.
+            // This makes it possible to control the effect from the main world.
+            // This plugin will take care of extracting it automatically.
+            // It's important to derive [`ExtractComponent`] on [`PostProcessingSettings`]
+            // for this plugin to work correctly.
+            ExtractComponentPlugin::<SimuSettings>::default(),
+            // The settings will also be the data used in the shader.
+            // This plugin will prepare the component for the GPU by creating a uniform buffer
+            // and writing the data to that buffer every frame.
+            UniformComponentPlugin::<SimuSettings>::default(),
+        ));
+
+        // Extract the game of life image resource from the main world into the render world
+        // for operation on by the compute shader and display on the sprite.
+        app.add_plugins(ExtractResourcePlugin::<SimuImages>::default());
+        app.add_plugins(ExtractResourcePlugin::<SimuTriggers>::default());
+        app.add_plugins(ExtractResourcePlugin::<SimuClock>::default());
+        app.init_resource::<SimuClock>();
+        app.init_asset::<LifePattern>();
+        app.init_asset_loader::<LifePatternLoader>();
+        app.add_event::<SimuReseed>();
+        app.init_resource::<SimuReadback>();
+        app.init_resource::<readback::SimuReadbackSettings>();
+        app.add_systems(
+            Startup,
+            (
+                populate_simu_plane_and_images,
+                readback::populate_simu_readback,
+            ),
+        );
+        app.add_systems(
+            Update,
+            (
+                (request_simu_reseed, seed::reseed_simu).chain(),
+                update_simu_rules,
+                (clock::control_simu_clock, clock::advance_simu_clock)
+                    .chain()
+                    .before(UiSystems),
+                brush::paint_simu_brushes,
+                readback::request_simu_readback,
+            ),
+        );
+        #[cfg(not(target_family = "wasm"))]
+        app.add_systems(Update, readback::dump_simu_snapshot);
+
+        // the render world counts the generations, the main world reads them
+        let generation = SimuGeneration::default();
+        app.insert_resource(generation.clone());
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.insert_resource(generation);
+        render_app.add_systems(
+            Render,
+            (
+                (copy_triggers, upload_simu_seed).chain(),
+                update_bind_groups,
+            )
+                .in_set(RenderSet::PrepareBindGroups),
+        );
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        render_graph.add_node(SimuNodes::Main, MainNode::default());
+        render_graph.add_node_edge(SimuNodes::Main, CameraDriverLabel);
+    }
+    fn finish(&self, app: &mut App) {
+        info!("** simu_finish **");
+
+        app.init_resource::<SimuTriggers>();
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.init_resource::<SimuPipeline>();
+    }
+}
+
+fn request_simu_reseed(mut reseeds: EventWriter<SimuReseed>, actions: Res<ActionState>) {
+    if actions.any.just_pressed(Action::Reseed) {
+        reseeds.write(SimuReseed);
+    }
+}
+
+/// Rules of a preset, for the red, green and blue channels
+fn preset_rules(index: usize) -> Result<[CellRule; 3], RuleParseError> {
+    let (_, texts) = RULE_PRESETS[index];
+    let mut rules = [CellRule::default(); 3];
+    for (rule, text) in rules.iter_mut().zip(texts) {
+        *rule = text.parse()?;
+    }
+    Ok(rules)
+}
+
+/// Parses the selected preset whenever the selection changes
+fn update_simu_rules(
+    mut settings: Query<&mut SimuSettings>,
+    mut current_index: Local<Option<usize>>,
+    ui_state: Res<UiState>,
+) {
+    if *current_index == Some(ui_state.rule_index) {
+        return;
+    }
+    let (name, _) = RULE_PRESETS[ui_state.rule_index];
+    let rules = match preset_rules(ui_state.rule_index) {
+        Ok(rules) => rules,
+        Err(err) => {
+            error!("invalid rule preset {}: {}", name, err);
+            return;
+        }
+    };
+    // only a parsed preset counts as applied, a failed one is tried again
+    *current_index = Some(ui_state.rule_index);
+    info!("** simu rules {} **", name);
+    for mut settings in &mut settings {
+        settings.rules = rules.each_ref().map(RuleUniform::from);
+    }
+}
+
+//////////////////////////////////////////////////////////////////////
+
+/// Image a holds the latest generation once the frame's passes are done, image b is scratch
+#[derive(Resource, Clone, ExtractResource)]
+struct SimuImages {
+    image_a: Handle<Image>,
+    image_b: Handle<Image>,
+}
+
+fn populate_simu_plane_and_images(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    use bevy::render::render_resource::*;
+
+    info!("** populate_simu_plane_and_images **");
+
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: TEXTURE_SIZE.0,
+            height: TEXTURE_SIZE.1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TEXTURE_FORMAT,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_descriptor.usage = TextureUsages::COPY_DST
+        | TextureUsages::COPY_SRC
+        | TextureUsages::STORAGE_BINDING
+        | TextureUsages::TEXTURE_BINDING;
+    image.sampler = bevy::image::ImageSampler::nearest();
+
+    let image_a = images.add(image.clone());
+    let image_b = images.add(image);
+
+    // magic plane
+    commands.spawn((
+        Mesh3d(
+            meshes.add(
+                Plane3d::default()
+                    .mesh()
+                    .size(SIMU_PLANE_SIZE, SIMU_PLANE_SIZE)
+                    .subdivisions(20),
+            ),
+        ),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            perceptual_roughness: 1.0,
+            metallic: 0.0,
+            base_color_texture: Some(image_a.clone()),
+            ..default()
+        })),
+        Transform::from_xyz(100.0, -0.25, -100.0),
+        SimuSettings::default(),
+    ));
+
+    // insert images
+    commands.insert_resource(SimuImages { image_a, image_b });
+}
+
+/// Texel coordinates under a world position, none away from the plane
+fn world_to_texel(plane_transform: &GlobalTransform, pos: Vec3, max_height: f32) -> Option<Vec2> {
+    let local = plane_transform.affine().inverse().transform_point3(pos);
+    // plane uvs go from 0 to 1 along local x and z
+    let uv = local.xz() / SIMU_PLANE_SIZE + 0.5;
+    let is_over_plane =
+        local.y.abs() < max_height && uv.cmpge(Vec2::ZERO).all() && uv.cmplt(Vec2::ONE).all();
+    is_over_plane.then(|| uv * Vec2::new(TEXTURE_SIZE.0 as f32, TEXTURE_SIZE.1 as f32))
+}
+
+//////////////////////////////////////////////////////////////////////
+
+#[derive(Resource, Clone, Default, ExtractResource)]
+struct SimuTriggers {
+    should_reinit: bool,
+    should_paint: bool,           // some brushes stamped cells this frame
+    upload: Option<Arc<Vec<u8>>>, // seed texels replacing image a
+}
+
+#[derive(Resource)]
+struct SimuPipeline {
+    simu_triggers: SimuTriggers,
+    group_layout: BindGroupLayout,
+    init_pipeline: CachedComputePipelineId,
+    update_pipeline: CachedComputePipelineId,
+    paint_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for SimuPipeline {
+    fn from_world(world: &mut World) -> Self {
+        use bevy::render::render_resource::*;
+
+        let render_device = world.resource::<RenderDevice>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let group_layout = render_device.create_bind_group_layout(
+            None,
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    texture_storage_2d(TEXTURE_FORMAT, StorageTextureAccess::ReadOnly),
+                    texture_storage_2d(TEXTURE_FORMAT, StorageTextureAccess::WriteOnly),
+                    uniform_buffer::<SimuSettings>(true),
+                ),
+            ),
+        );
+
+        let shader: Handle<Shader> = world.load_asset(SHADER_PATH);
+
+        let init_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some(Cow::from("init_pipeline")),
+            layout: vec![group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader: shader.clone(),
+            shader_defs: vec![],
+            entry_point: Cow::from("init"),
+            zero_initialize_workgroup_memory: false,
+        });
+
+        let update_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some(Cow::from("update_pipeline")),
+            layout: vec![group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader: shader.clone(),
+            shader_defs: vec![],
+            entry_point: Cow::from("update"),
+            zero_initialize_workgroup_memory: false,
+        });
+
+        let paint_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some(Cow::from("paint_pipeline")),
+            layout: vec![group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: vec![],
+            entry_point: Cow::from("paint"),
+            zero_initialize_workgroup_memory: false,
+        });
+
+        SimuPipeline {
+            simu_triggers: SimuTriggers::default(),
+            group_layout,
+            init_pipeline,
+            update_pipeline,
+            paint_pipeline,
+        }
+    }
+}
+
+fn copy_triggers(simu_triggers: Res<SimuTriggers>, mut simu_pipeline: ResMut<SimuPipeline>) {
+    simu_pipeline.simu_triggers = simu_triggers.clone();
+}
+
+/// Writes the seed into image a, the next step reads from it
+fn upload_simu_seed(
+    simu_pipeline: Res<SimuPipeline>,
+    simu_images: Res<SimuImages>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    render_queue: Res<RenderQueue>,
+    generation: Res<SimuGeneration>,
+) {
+    use bevy::render::render_resource::*;
+
+    let Some(data) = &simu_pipeline.simu_triggers.upload else {
+        return;
+    };
+    let Some(gpu_image) = gpu_images.get(&simu_images.image_a) else {
+        return;
+    };
+    render_queue.write_texture(
+        TexelCopyTextureInfo {
+            texture: &gpu_image.texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        data,
+        TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(TEXTURE_SIZE.0 * TEXEL_SIZE as u32),
+            rows_per_image: None,
+        },
+        Extent3d {
+            width: TEXTURE_SIZE.0,
+            height: TEXTURE_SIZE.1,
+            depth_or_array_layers: 1,
+        },
+    );
+    generation.reset();
+}
+
+//////////////////////////////////////////////////////////////////////
+
+#[derive(Resource)]
+struct SimuBindGroups {
+    group_a_to_b: BindGroup,
+    group_b_to_a: BindGroup,
+}
+
+fn update_bind_groups(
+    mut commands: Commands,
+    simu_settings: Res<ComponentUniforms<SimuSettings>>,
+    simu_pipeline: Res<SimuPipeline>,
+    simu_images: Res<SimuImages>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    render_device: Res<RenderDevice>,
+) {
+    let simu_binding = simu_settings.uniforms().binding();
+    assert!(simu_binding.is_some());
+
+    let view_a = gpu_images.get(&simu_images.image_a).unwrap();
+    let view_b = gpu_images.get(&simu_images.image_b).unwrap();
+    let group_a_to_b = render_device.create_bind_group(
+        Some("group_a_to_b"),
+        &simu_pipeline.group_layout,
+        &BindGroupEntries::sequential((
+            &view_a.texture_view,
+            &view_b.texture_view,
+            simu_binding.clone().unwrap(),
+        )),
+    );
+    let group_b_to_a = render_device.create_bind_group(
+        Some("group_b_to_a"),
+        &simu_pipeline.group_layout,
+        &BindGroupEntries::sequential((
+            &view_b.texture_view,
+            &view_a.texture_view,
+            simu_binding.unwrap(),
+        )),
+    );
+
+    // insert bind groups
+    commands.insert_resource(SimuBindGroups {
+        group_a_to_b,
+        group_b_to_a,
+    });
+}
+
+//////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+enum MainState {
+    #[default]
+    Loading,
+    Init,
+    Update,
+}
+
+#[derive(Default)]
+struct MainNode {
+    state: MainState,
+    steps: u32,   // update dispatches for the current frame
+    paints: bool, // brush stamps are copied in before the steps
+}
+
+impl Node for MainNode {
+    fn update(&mut self, world: &mut World) {
+        use bevy::render::render_resource::*;
+
+        let pipeline = world.resource::<SimuPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let should_reinit = pipeline.simu_triggers.should_reinit;
+        let should_paint = pipeline.simu_triggers.should_paint;
+
+        // if the corresponding pipeline has loaded, transition to the next stage
+        match self.state {
+            MainState::Loading => {
+                let init_ok = matches!(
+                    pipeline_cache.get_compute_pipeline_state(pipeline.init_pipeline),
+                    CachedPipelineState::Ok(_)
+                );
+                let update_ok = matches!(
+                    pipeline_cache.get_compute_pipeline_state(pipeline.update_pipeline),
+                    CachedPipelineState::Ok(_)
+                );
+                let paint_ok = matches!(
+                    pipeline_cache.get_compute_pipeline_state(pipeline.paint_pipeline),
+                    CachedPipelineState::Ok(_)
+                );
+                if init_ok && update_ok && paint_ok {
+                    self.state = MainState::Init;
+                }
+            }
+            MainState::Init | MainState::Update => {
+                self.state = match should_reinit {
+                    false => MainState::Update,
+                    true => MainState::Init,
+                };
+            }
+        };
+
+        // uploaded seeds replace image a, no init pass should overwrite them
+        let is_uploaded = pipeline.simu_triggers.upload.is_some();
+        if is_uploaded && !matches!(self.state, MainState::Loading) {
+            self.state = MainState::Update;
+        }
+
+        self.paints = should_paint && matches!(self.state, MainState::Update);
+        self.steps = match self.state {
+            MainState::Update => world
+                .get_resource::<SimuClock>()
+                .map_or(0, |clock| clock.steps()),
+            _ => 0,
+        };
+    }
+
+    fn run(
+        &self,
+        _graph_context: &mut bevy::render::render_graph::RenderGraphContext,
+        render_context: &mut bevy::render::renderer::RenderContext,
+        world: &World,
+    ) -> Result<(), bevy::render::render_graph::NodeRunError> {
+        use bevy::render::render_resource::*;
+
+        let pipeline = world.resource::<SimuPipeline>();
+        let bind_groups = world.resource::<SimuBindGroups>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let generation = world.resource::<SimuGeneration>();
+        let workgroups = (
+            TEXTURE_SIZE.0 / WORKGROUP_SIZE,
+            TEXTURE_SIZE.1 / WORKGROUP_SIZE,
+        );
+
+        // every pass reads the image written by the previous one, starting from image a
+        let mut passes = 0;
+        {
+            let mut pass = render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor::default());
+
+            // select the pipeline based on the current state
+            match self.state {
+                MainState::Loading => {}
+                MainState::Init => {
+                    let init_pipeline = pipeline_cache
+                        .get_compute_pipeline(pipeline.init_pipeline)
+                        .unwrap();
+                    pass.set_bind_group(0, &bind_groups.group_a_to_b, &[0]);
+                    pass.set_pipeline(init_pipeline);
+                    pass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+                    passes = 1;
+                    generation.reset();
+                }
+                MainState::Update => {
+                    let update_pipeline = pipeline_cache
+                        .get_compute_pipeline(pipeline.update_pipeline)
+                        .unwrap();
+                    let paint_pipeline = pipeline_cache
+                        .get_compute_pipeline(pipeline.paint_pipeline)
+                        .unwrap();
+                    passes = self.steps + self.paints as u32;
+                    for index in 0..passes {
+                        let is_paint = self.paints && index == 0;
+                        pass.set_pipeline(if is_paint {
+                            paint_pipeline
+                        } else {
+                            update_pipeline
+                        });
+                        pass.set_bind_group(
+                            0,
+                            if index % 2 == 0 {
+                                &bind_groups.group_a_to_b
+                            } else {
+                                &bind_groups.group_b_to_a
+                            },
+                            &[0],
+                        );
+                        pass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+                    }
+                    generation.advance(self.steps);
+                }
+            };
+        }
+
+        // an odd number of passes ended in image b, the material and the readbacks show image a
+        if passes % 2 == 1 {
+            let simu_images = world.resource::<SimuImages>();
+            let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+            if let (Some(view_a), Some(view_b)) = (
+                gpu_images.get(&simu_images.image_a),
+                gpu_images.get(&simu_images.image_b),
+            ) {
+                render_context.command_encoder().copy_texture_to_texture(
+                    view_b.texture.as_image_copy(),
+                    view_a.texture.as_image_copy(),
+                    view_a.size,
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
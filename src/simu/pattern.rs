@@ -0,0 +1,288 @@
+use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::prelude::*;
+
+use thiserror::Error;
+
+/// Life pattern read from a run length encoded or plaintext file
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct LifePattern {
+    pub name: Option<String>,
+    pub rule: Option<String>,     // rule string from the rle header
+    pub size: UVec2,              // declared size, grown to fit every cell
+    pub cells: Vec<(UVec2, u32)>, // offset from the top left corner and state, dead cells omitted
+}
+
+#[derive(Debug, Error)]
+pub enum LifePatternError {
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+    #[error("unsupported pattern extension {0:?}")]
+    Extension(String),
+}
+
+fn syntax_error(line: usize, message: impl Into<String>) -> LifePatternError {
+    LifePatternError::Syntax {
+        line: line + 1,
+        message: message.into(),
+    }
+}
+
+impl LifePattern {
+    fn from_cells(
+        name: Option<String>,
+        rule: Option<String>,
+        size: UVec2,
+        cells: Vec<(UVec2, u32)>,
+    ) -> Self {
+        let size = cells.iter().fold(size, |size, (pos, _)| size.max(*pos + 1));
+        Self {
+            name,
+            rule,
+            size,
+            cells,
+        }
+    }
+
+    /// Parses "#N" names, the "x = 3, y = 3, rule = B3/S23" header and the run length encoded body,
+    /// multi-state patterns use "." for dead cells and "A" to "X", "pA" to "yO" for the other states
+    pub fn from_rle(text: &str) -> Result<Self, LifePatternError> {
+        let mut name = None;
+        let mut rule = None;
+        let mut size = None;
+        let mut cells = Vec::new();
+        let mut pos = UVec2::ZERO;
+        let mut count: Option<u32> = None;
+        let mut prefix: Option<u32> = None;
+        'lines: for (line, text) in text.lines().enumerate() {
+            let text = text.trim();
+            if let Some(comment) = text.strip_prefix('#') {
+                if let Some(value) = comment.strip_prefix('N') {
+                    name = Some(value.trim().to_owned());
+                }
+                continue;
+            }
+            if text.is_empty() {
+                continue;
+            }
+            if size.is_none() {
+                let (header_size, header_rule) = parse_rle_header(line, text)?;
+                size = Some(header_size);
+                rule = header_rule;
+                continue;
+            }
+            for cc in text.chars() {
+                if let Some(digit) = cc.to_digit(10) {
+                    count = Some(count.unwrap_or(0) * 10 + digit);
+                    continue;
+                }
+                if ('p'..='y').contains(&cc) {
+                    prefix = Some(cc as u32 - 'p' as u32 + 1);
+                    continue;
+                }
+                let run = count.take().unwrap_or(1);
+                let state = match (prefix.take(), cc) {
+                    (None, 'b' | '.') => 0,
+                    (None, 'o') => 1,
+                    (prefix, 'A'..='X') => prefix.unwrap_or(0) * 24 + cc as u32 - 'A' as u32 + 1,
+                    (None, '$') => {
+                        pos = UVec2::new(0, pos.y + run);
+                        continue;
+                    }
+                    (None, '!') => break 'lines,
+                    (None, cc) if cc.is_whitespace() => continue,
+                    _ => return Err(syntax_error(line, format!("unexpected {:?}", cc))),
+                };
+                if state > 0 {
+                    cells.extend((0..run).map(|kk| (pos + UVec2::X * kk, state)));
+                }
+                pos.x += run;
+            }
+        }
+        let Some(size) = size else {
+            return Err(syntax_error(0, "missing header"));
+        };
+        Ok(Self::from_cells(name, rule, size, cells))
+    }
+
+    /// Parses "!Name:" comments and rows of "." dead and "O" live cells
+    pub fn from_plaintext(text: &str) -> Result<Self, LifePatternError> {
+        let mut name = None;
+        let mut cells = Vec::new();
+        let mut row = 0;
+        let mut width = 0;
+        for (line, text) in text.lines().enumerate() {
+            let text = text.trim_end();
+            if let Some(comment) = text.strip_prefix('!') {
+                if let Some(value) = comment.strip_prefix("Name:") {
+                    name = Some(value.trim().to_owned());
+                }
+                continue;
+            }
+            for (column, cc) in text.chars().enumerate() {
+                match cc {
+                    '.' => {}
+                    'O' | '*' => cells.push((UVec2::new(column as u32, row), 1)),
+                    _ => return Err(syntax_error(line, format!("unexpected {:?}", cc))),
+                }
+            }
+            width = width.max(text.chars().count() as u32);
+            row += 1;
+        }
+        Ok(Self::from_cells(name, None, UVec2::new(width, row), cells))
+    }
+}
+
+/// Size and rule of a "x = 3, y = 3, rule = B3/S23" header, the rule is kept verbatim as
+/// Larger than Life rules contain commas
+fn parse_rle_header(line: usize, text: &str) -> Result<(UVec2, Option<String>), LifePatternError> {
+    let (entries, rule) = match text.find("rule") {
+        Some(index) => {
+            let Some(rule) = text[index + "rule".len()..].trim_start().strip_prefix('=') else {
+                return Err(syntax_error(line, "invalid header rule"));
+            };
+            (&text[..index], Some(rule.trim().to_owned()))
+        }
+        None => (text, None),
+    };
+    let mut size = (None, None);
+    for entry in entries.split(',').map(str::trim) {
+        if entry.is_empty() {
+            continue;
+        }
+        let invalid = || syntax_error(line, format!("invalid header entry {:?}", entry));
+        let (key, value) = entry.split_once('=').ok_or_else(invalid)?;
+        let value = value.trim().parse::<u32>().map_err(|_| invalid())?;
+        match key.trim() {
+            "x" => size.0 = Some(value),
+            "y" => size.1 = Some(value),
+            _ => return Err(invalid()),
+        }
+    }
+    let (Some(width), Some(height)) = size else {
+        return Err(syntax_error(line, "missing x or y in header"));
+    };
+    Ok((UVec2::new(width, height), rule))
+}
+
+#[derive(Default)]
+pub struct LifePatternLoader;
+
+#[derive(Debug, Error)]
+pub enum LifePatternLoaderError {
+    #[error("could not read pattern: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("pattern is not utf8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("could not parse pattern: {0}")]
+    Pattern(#[from] LifePatternError),
+}
+
+impl AssetLoader for LifePatternLoader {
+    type Asset = LifePattern;
+    type Settings = ();
+    type Error = LifePatternLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes)?;
+        let extension = load_context
+            .path()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        let pattern = match extension {
+            "rle" => LifePattern::from_rle(&text)?,
+            "cells" => LifePattern::from_plaintext(&text)?,
+            _ => return Err(LifePatternError::Extension(extension.to_owned()).into()),
+        };
+        Ok(pattern)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rle", "cells"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rle_glider() {
+        let pattern = LifePattern::from_rle(
+            "#N Glider\n#C comment\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n",
+        )
+        .unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(pattern.size, UVec2::new(3, 3));
+        let cells = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| (UVec2::new(x, y), 1));
+        assert_eq!(pattern.cells, cells);
+    }
+
+    #[test]
+    fn rle_gun_line_wrap() {
+        let text = "#N Gosper glider gun
+x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!
+";
+        let pattern = LifePattern::from_rle(text).unwrap();
+        assert_eq!(pattern.size, UVec2::new(36, 9));
+        assert_eq!(pattern.cells.len(), 36);
+        // the run started on the previous line carries over
+        assert!(pattern.cells.contains(&(UVec2::new(22, 5), 1)));
+        assert!(pattern.cells.contains(&(UVec2::new(24, 5), 1)));
+        // so do run counts split from their tag
+        let pattern = LifePattern::from_rle("x = 12, y = 1\n1\n2o!").unwrap();
+        assert_eq!(pattern.cells.len(), 12);
+    }
+
+    #[test]
+    fn rle_multi_state_letters() {
+        let pattern = LifePattern::from_rle("x = 6, y = 2, rule = B2/S/C3\nA.2B$pA.yO!").unwrap();
+        assert_eq!(pattern.rule.as_deref(), Some("B2/S/C3"));
+        let cells = [
+            ((0, 0), 1),
+            ((2, 0), 2),
+            ((3, 0), 2),
+            ((0, 1), 25),
+            ((2, 1), 255),
+        ]
+        .map(|((x, y), state)| (UVec2::new(x, y), state));
+        assert_eq!(pattern.cells, cells);
+        assert_eq!(pattern.size, UVec2::new(6, 2));
+    }
+
+    #[test]
+    fn rle_larger_than_life_rule() {
+        let pattern =
+            LifePattern::from_rle("x = 2, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM\n2o!").unwrap();
+        assert_eq!(pattern.rule.as_deref(), Some("R5,C0,M1,S34..58,B34..45,NM"));
+        assert_eq!(pattern.size, UVec2::new(2, 1));
+    }
+
+    #[test]
+    fn rle_missing_header() {
+        assert!(LifePattern::from_rle("bo$2bo$3o!").is_err());
+        assert!(LifePattern::from_rle("#N Glider\n").is_err());
+        assert!(LifePattern::from_rle("x = 3, rule = B3/S23\nbo$2bo$3o!").is_err());
+    }
+
+    #[test]
+    fn plaintext_glider() {
+        let pattern = LifePattern::from_plaintext("!Name: Glider\n!\n.O\n..O\nOOO\n").unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.rule, None);
+        assert_eq!(pattern.size, UVec2::new(3, 3));
+        let cells = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| (UVec2::new(x, y), 1));
+        assert_eq!(pattern.cells, cells);
+        assert!(LifePattern::from_plaintext(".O\nxO\n").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use super::clock::SimuGeneration;
+use super::{world_to_texel, SimuImages, SimuSettings, TEXEL_SIZE, TEXTURE_SIZE};
+#[cfg(not(target_family = "wasm"))]
+use crate::actions::{Action, ActionState};
+
+use bevy::prelude::*;
+use bevy::render::gpu_readback::{Readback, ReadbackComplete};
+use bevy::render::renderer::RenderDevice;
+
+const READBACK_INTERVAL: f32 = 0.25; // s, each readback copies the whole texture
+const SAMPLE_HEIGHT: f32 = 4.0; // m, positions further from the plane sample nothing
+#[cfg(not(target_family = "wasm"))]
+const SNAPSHOT_DUMP_PATH: &str = "simu_snapshot.ppm";
+
+/// Latest simulation state read back from the gpu, a few frames behind the display
+#[derive(Resource, Default)]
+pub struct SimuReadback {
+    pub generation: u64,              // approximate, counted when the data arrived
+    pub populations: [u32; 3],        // live cells per channel
+    pub bounds: [Option<URect>; 3],   // texels holding live cells per channel, inclusive
+    pub snapshot: Option<Vec<Vec4>>,  // raw texels, row major, only with keep_snapshot
+    alive: Vec<u8>,                   // bit per channel for every texel
+    plane_transform: GlobalTransform, // maps world positions to texels
+}
+
+impl SimuReadback {
+    /// Live cells per channel under a world position, none away from the plane or before any readback
+    pub fn sample(&self, pos: Vec3) -> Option<[bool; 3]> {
+        let texel = world_to_texel(&self.plane_transform, pos, SAMPLE_HEIGHT)?.as_uvec2();
+        let bits = self
+            .alive
+            .get((texel.y * TEXTURE_SIZE.0 + texel.x) as usize)?;
+        Some([0, 1, 2].map(|channel| bits & (1 << channel) != 0))
+    }
+}
+
+#[derive(Resource)]
+pub struct SimuReadbackSettings {
+    pub keep_snapshot: bool,
+    timer: Timer,
+}
+
+impl Default for SimuReadbackSettings {
+    fn default() -> Self {
+        Self {
+            keep_snapshot: false,
+            timer: Timer::from_seconds(READBACK_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Holds the readback component while a copy is requested
+#[derive(Component)]
+struct SimuReadbackTarget;
+
+pub fn populate_simu_readback(mut commands: Commands) {
+    commands
+        .spawn((SimuReadbackTarget, Name::new("simu_readback")))
+        .observe(on_simu_readback);
+}
+
+/// Requests a copy for a single frame every interval, the readback repeats while the component lives
+pub fn request_simu_readback(
+    mut commands: Commands,
+    mut settings: ResMut<SimuReadbackSettings>,
+    targets: Query<(Entity, Has<Readback>), With<SimuReadbackTarget>>,
+    simu_images: Option<Res<SimuImages>>,
+    time: Res<Time>,
+) {
+    let Some(simu_images) = simu_images else {
+        return;
+    };
+    let is_due = settings.timer.tick(time.delta()).just_finished();
+    for (entity, is_requested) in &targets {
+        if is_requested {
+            commands.entity(entity).remove::<Readback>();
+        } else if is_due {
+            // readback copies are submitted after the render graph, once the simulation node has
+            // left the frame's last generation in image a
+            commands
+                .entity(entity)
+                .insert(Readback::texture(simu_images.image_a.clone()));
+        }
+    }
+}
+
+fn on_simu_readback(
+    trigger: Trigger<ReadbackComplete>,
+    mut readback: ResMut<SimuReadback>,
+    settings: Res<SimuReadbackSettings>,
+    planes: Query<&GlobalTransform, With<SimuSettings>>,
+    generation: Res<SimuGeneration>,
+) {
+    let (width, height) = (TEXTURE_SIZE.0 as usize, TEXTURE_SIZE.1 as usize);
+    let bytes_per_row = RenderDevice::align_copy_bytes_per_row(width * TEXEL_SIZE);
+    let data = &trigger.event().0;
+    if data.len() < bytes_per_row * height {
+        warn!("simu readback too short, {} bytes", data.len());
+        return;
+    }
+
+    let readback = readback.as_mut();
+    readback.populations = [0; 3];
+    readback.bounds = [None; 3];
+    readback.alive.clear();
+    readback.alive.reserve(width * height);
+    let mut snapshot = settings
+        .keep_snapshot
+        .then(|| Vec::with_capacity(width * height));
+    for jj in 0..height {
+        let row = &data[jj * bytes_per_row..][..width * TEXEL_SIZE];
+        for (ii, texel) in row.chunks_exact(TEXEL_SIZE).enumerate() {
+            let value = Vec4::from_array(std::array::from_fn(|kk| {
+                f32::from_le_bytes(texel[kk * 4..kk * 4 + 4].try_into().unwrap())
+            }));
+            let mut bits = 0;
+            for channel in 0..3 {
+                // dying cells of multi-state rules are not alive
+                if value[channel] < 1.0 {
+                    continue;
+                }
+                bits |= 1 << channel;
+                readback.populations[channel] += 1;
+                let point = UVec2::new(ii as u32, jj as u32);
+                let bounds =
+                    readback.bounds[channel].get_or_insert(URect::from_corners(point, point));
+                *bounds = bounds.union_point(point);
+            }
+            readback.alive.push(bits);
+            if let Some(snapshot) = snapshot.as_mut() {
+                snapshot.push(value);
+            }
+        }
+    }
+    readback.snapshot = snapshot;
+    readback.generation = generation.get();
+    if let Ok(plane_transform) = planes.single() {
+        readback.plane_transform = *plane_transform;
+    }
+    debug!(
+        "simu readback at generation {}, populations {:?}, bounds {:?}",
+        readback.generation, readback.populations, readback.bounds
+    );
+}
+
+/// The dump action keeps the next snapshot and writes it as a binary ppm image
+#[cfg(not(target_family = "wasm"))]
+pub fn dump_simu_snapshot(
+    mut settings: ResMut<SimuReadbackSettings>,
+    mut is_pending: Local<bool>,
+    readback: Res<SimuReadback>,
+    actions: Res<ActionState>,
+) {
+    if actions.any.just_pressed(Action::DumpSnapshot) {
+        settings.keep_snapshot = true;
+        *is_pending = true;
+    }
+    if !*is_pending || !readback.is_changed() {
+        return;
+    }
+    let Some(snapshot) = &readback.snapshot else {
+        return;
+    };
+    *is_pending = false;
+    settings.keep_snapshot = false;
+
+    let mut bytes = format!("P6\n{} {}\n255\n", TEXTURE_SIZE.0, TEXTURE_SIZE.1).into_bytes();
+    for value in snapshot {
+        bytes.extend(
+            value
+                .xyz()
+                .to_array()
+                .map(|aa| (aa.clamp(0.0, 1.0) * 255.0) as u8),
+        );
+    }
+    match std::fs::write(SNAPSHOT_DUMP_PATH, bytes) {
+        Ok(()) => info!(
+            "simu snapshot at generation {} dumped to {}",
+            readback.generation, SNAPSHOT_DUMP_PATH
+        ),
+        Err(err) => error!("could not dump simu snapshot: {}", err),
+    }
+}
@@ -0,0 +1,256 @@
+use bevy::render::render_resource::ShaderType;
+
+use thiserror::Error;
+
+use std::str::FromStr;
+
+const MAX_RADIUS: u32 = 7; // cells, keeps the neighbourhood loop affordable
+const MAX_STATES: u32 = 256;
+
+/// Neighbour counts a transition applies to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Counts {
+    Mask(u32),       // bit n is set when n neighbours match
+    Range(u32, u32), // inclusive, used by Larger than Life rules
+}
+
+/// Outer totalistic rule, with dying states for Generations rules
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellRule {
+    pub birth: Counts,
+    pub survival: Counts,
+    pub states: u32,          // 2 for life-like rules
+    pub radius: u32,          // 1 for the Moore neighbourhood
+    pub include_center: bool, // the cell counts as its own neighbour
+}
+
+impl Default for CellRule {
+    fn default() -> Self {
+        "B3/S23".parse().unwrap()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RuleParseError {
+    #[error("unexpected token {0:?}")]
+    Token(String),
+    #[error("invalid neighbour count {0:?}")]
+    Count(String),
+    #[error("missing birth counts")]
+    MissingBirth,
+    #[error("radius {0} is not in 1..={MAX_RADIUS}")]
+    Radius(u32),
+    #[error("{0} states is not in 2..={MAX_STATES}")]
+    States(u32),
+    #[error("unsupported neighbourhood {0:?}")]
+    Neighborhood(String),
+}
+
+/// Parses "B3/S23", "B2/S/C3" (or "B2/S/3") and "R5,C0,M1,S34..58,B34..45,NM" rule strings
+impl FromStr for CellRule {
+    type Err = RuleParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let is_larger_than_life = text.len() > 1
+            && text.starts_with(['R', 'r'])
+            && text[1..].starts_with(|cc: char| cc.is_ascii_digit());
+        let rule = if is_larger_than_life {
+            parse_larger_than_life(text)?
+        } else {
+            parse_generations(text)?
+        };
+        if rule.radius < 1 || rule.radius > MAX_RADIUS {
+            return Err(RuleParseError::Radius(rule.radius));
+        }
+        if rule.states < 2 || rule.states > MAX_STATES {
+            return Err(RuleParseError::States(rule.states));
+        }
+        Ok(rule)
+    }
+}
+
+fn parse_number(text: &str) -> Result<u32, RuleParseError> {
+    text.parse()
+        .map_err(|_| RuleParseError::Count(text.to_owned()))
+}
+
+/// Single digit counts, each one sets the matching bit
+fn parse_mask(text: &str) -> Result<Counts, RuleParseError> {
+    let mut mask = 0;
+    for cc in text.chars() {
+        match cc.to_digit(10) {
+            Some(count) if count <= 8 => mask |= 1 << count,
+            _ => return Err(RuleParseError::Count(text.to_owned())),
+        }
+    }
+    Ok(Counts::Mask(mask))
+}
+
+fn parse_range(text: &str) -> Result<Counts, RuleParseError> {
+    match text.split_once("..") {
+        Some((min, max)) => Ok(Counts::Range(parse_number(min)?, parse_number(max)?)),
+        None => {
+            let count = parse_number(text)?;
+            Ok(Counts::Range(count, count))
+        }
+    }
+}
+
+/// Life-like and Generations rules, parts without prefix follow the S/B/C order
+fn parse_generations(text: &str) -> Result<CellRule, RuleParseError> {
+    let mut birth = None;
+    let mut survival = Counts::Mask(0);
+    let mut states = 2;
+    for (index, part) in text.split('/').enumerate() {
+        let (kind, value) = match part.chars().next() {
+            Some(cc) if cc.is_ascii_alphabetic() => (cc.to_ascii_uppercase(), &part[1..]),
+            _ => (['S', 'B', 'C'].get(index).copied().unwrap_or('?'), part),
+        };
+        match kind {
+            'B' => birth = Some(parse_mask(value)?),
+            'S' => survival = parse_mask(value)?,
+            'C' | 'G' => states = parse_number(value)?,
+            _ => return Err(RuleParseError::Token(part.to_owned())),
+        }
+    }
+    Ok(CellRule {
+        birth: birth.ok_or(RuleParseError::MissingBirth)?,
+        survival,
+        states,
+        radius: 1,
+        include_center: false,
+    })
+}
+
+/// Golly style Larger than Life rules, C0 and C2 both give two states
+fn parse_larger_than_life(text: &str) -> Result<CellRule, RuleParseError> {
+    let mut rule = CellRule {
+        birth: Counts::Range(1, 0),
+        survival: Counts::Range(1, 0),
+        states: 2,
+        radius: 1,
+        include_center: false,
+    };
+    let mut has_birth = false;
+    for token in text.split(',').map(str::trim) {
+        let Some(kind) = token.chars().next() else {
+            return Err(RuleParseError::Token(token.to_owned()));
+        };
+        let value = &token[kind.len_utf8()..];
+        match kind.to_ascii_uppercase() {
+            'R' => rule.radius = parse_number(value)?,
+            'C' => rule.states = parse_number(value)?.max(2),
+            'M' => rule.include_center = parse_number(value)? != 0,
+            'S' => rule.survival = parse_range(value)?,
+            'B' => {
+                rule.birth = parse_range(value)?;
+                has_birth = true;
+            }
+            'N' if value.eq_ignore_ascii_case("M") => {}
+            'N' => return Err(RuleParseError::Neighborhood(value.to_owned())),
+            _ => return Err(RuleParseError::Token(token.to_owned())),
+        }
+    }
+    if !has_birth {
+        return Err(RuleParseError::MissingBirth);
+    }
+    Ok(rule)
+}
+
+//////////////////////////////////////////////////////////////////////
+
+/// Rule layout in the simulation uniform, empty ranges have min > max
+#[derive(ShaderType, Clone, Copy, Default, Debug)]
+pub struct RuleUniform {
+    birth_mask: u32,
+    survival_mask: u32,
+    birth_min: u32,
+    birth_max: u32,
+    survival_min: u32,
+    survival_max: u32,
+    states: u32,
+    radius: u32,
+    include_center: u32,
+    // uniform array elements are 16 bytes aligned
+    padding_0: u32,
+    padding_1: u32,
+    padding_2: u32,
+}
+
+impl From<&CellRule> for RuleUniform {
+    fn from(rule: &CellRule) -> Self {
+        let split = |counts: Counts| match counts {
+            Counts::Mask(mask) => (mask, 1, 0),
+            Counts::Range(min, max) => (0, min, max),
+        };
+        let (birth_mask, birth_min, birth_max) = split(rule.birth);
+        let (survival_mask, survival_min, survival_max) = split(rule.survival);
+        Self {
+            birth_mask,
+            survival_mask,
+            birth_min,
+            birth_max,
+            survival_min,
+            survival_max,
+            states: rule.states,
+            radius: rule.radius,
+            include_center: rule.include_center as u32,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn life_like() {
+        let rule: CellRule = "B3/S23".parse().unwrap();
+        assert_eq!(rule.birth, Counts::Mask(1 << 3));
+        assert_eq!(rule.survival, Counts::Mask(1 << 2 | 1 << 3));
+        assert_eq!(rule.states, 2);
+        assert_eq!(rule.radius, 1);
+        assert!(!rule.include_center);
+        assert_eq!("b3/s23".parse::<CellRule>().unwrap(), rule);
+    }
+
+    #[test]
+    fn generations() {
+        let rule: CellRule = "B2/S/C3".parse().unwrap();
+        assert_eq!(rule.birth, Counts::Mask(1 << 2));
+        assert_eq!(rule.survival, Counts::Mask(0));
+        assert_eq!(rule.states, 3);
+        // parts without prefix follow the S/B/C order
+        assert_eq!("/2/3".parse::<CellRule>().unwrap(), rule);
+    }
+
+    #[test]
+    fn larger_than_life() {
+        let rule: CellRule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert_eq!(rule.birth, Counts::Range(34, 45));
+        assert_eq!(rule.survival, Counts::Range(34, 58));
+        assert_eq!(rule.states, 2);
+        assert_eq!(rule.radius, 5);
+        assert!(rule.include_center);
+    }
+
+    #[test]
+    fn malformed() {
+        for text in [
+            "",
+            "S23",
+            "B9/S23",
+            "B3/S2x",
+            "B3/S23/C1",
+            "B3/S23/X4",
+            "R8,C0,M0,S1..2,B3",
+            "R5,C0,M1,S34..58",
+            "R5,C0,M1,S34..58,B34..45,NN",
+            "R5,C0,M1,S34..,B34..45,NM",
+        ] {
+            assert!(text.parse::<CellRule>().is_err(), "{:?} parsed", text);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use super::pattern::LifePattern;
+use super::rule::CellRule;
+use super::{preset_rules, SimuTriggers, TEXTURE_SIZE};
+use crate::ui::UiState;
+
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+
+use std::sync::Arc;
+
+/// Pattern placed by a seed
+#[derive(Clone, Copy, Debug)]
+pub struct SeedPattern {
+    pub path: &'static str,
+    pub offset: IVec2, // texels from the texture center to the pattern center
+    pub channel: usize,
+}
+
+const fn place(path: &'static str, x: i32, y: i32, channel: usize) -> SeedPattern {
+    SeedPattern {
+        path,
+        offset: IVec2::new(x, y),
+        channel,
+    }
+}
+
+// the world origin, where the spiders start, is at texel offset (-256, 256)
+const GUN: &str = "patterns/gosper_glider_gun.rle";
+const ACORN: &str = "patterns/acorn.rle";
+const R_PENTOMINO: &str = "patterns/r_pentomino.cells";
+const PULSAR: &str = "patterns/pulsar.rle";
+const GLIDER: &str = "patterns/glider.cells";
+
+/// Seeds selectable from the ui, noise when there are no patterns
+pub const SEED_PRESETS: &[(&str, &[SeedPattern])] = &[
+    ("noise", &[]),
+    (
+        "guns",
+        &[
+            place(GUN, -256, 220, 0),
+            place(GUN, -200, 300, 1),
+            place(GUN, -310, 290, 2),
+        ],
+    ),
+    (
+        "methuselahs",
+        &[
+            place(ACORN, -256, 256, 0),
+            place(R_PENTOMINO, -200, 200, 1),
+            place(ACORN, -310, 310, 2),
+        ],
+    ),
+    (
+        "oscillators",
+        &[
+            place(PULSAR, -276, 256, 0),
+            place(PULSAR, -236, 256, 1),
+            place(PULSAR, -256, 236, 2),
+            place(GLIDER, -220, 220, 0),
+            place(GLIDER, -290, 290, 1),
+        ],
+    ),
+];
+
+/// Reseeds the simulation with the seed selected in the ui
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SimuReseed;
+
+/// Requested on reseed or seed change, uploaded once every pattern is loaded
+pub fn reseed_simu(
+    mut reseeds: EventReader<SimuReseed>,
+    mut simu_triggers: ResMut<SimuTriggers>,
+    mut pending: Local<Option<Vec<(Handle<LifePattern>, SeedPattern)>>>,
+    mut current_index: Local<Option<usize>>,
+    ui_state: Res<UiState>,
+    server: Res<AssetServer>,
+    patterns: Res<Assets<LifePattern>>,
+) {
+    simu_triggers.should_reinit = false;
+    simu_triggers.upload = None;
+
+    // several sources may request the same reseed
+    let mut is_requested = reseeds.read().count() > 0;
+    if *current_index != Some(ui_state.seed_index) {
+        // the initial noise needs no reseed
+        is_requested |= current_index.is_some();
+        *current_index = Some(ui_state.seed_index);
+    }
+    if is_requested {
+        let (name, seeds) = SEED_PRESETS[ui_state.seed_index];
+        info!("** reseed simu with {} **", name);
+        if seeds.is_empty() {
+            simu_triggers.should_reinit = true;
+            *pending = None;
+        } else {
+            *pending = Some(
+                seeds
+                    .iter()
+                    .map(|seed| (server.load(seed.path), *seed))
+                    .collect(),
+            );
+        }
+    }
+
+    let Some(seeds) = pending.as_ref() else {
+        return;
+    };
+    let mut is_loaded = true;
+    for (handle, seed) in seeds {
+        match server.load_state(handle) {
+            LoadState::Loaded => {}
+            LoadState::Failed(err) => {
+                error!("could not load pattern {}: {}", seed.path, err);
+                *pending = None;
+                return;
+            }
+            _ => is_loaded = false,
+        }
+    }
+    if !is_loaded {
+        return;
+    }
+    let placed: Vec<(&LifePattern, SeedPattern)> = seeds
+        .iter()
+        .filter_map(|(handle, seed)| Some((patterns.get(handle)?, *seed)))
+        .collect();
+    simu_triggers.upload = Some(Arc::new(seed_texels(&placed, ui_state.rule_index)));
+    *pending = None;
+}
+
+/// Dead texels with the patterns stamped, encoded like the shader for multi-state rules
+fn seed_texels(placed: &[(&LifePattern, SeedPattern)], rule_index: usize) -> Vec<u8> {
+    let (width, height) = (TEXTURE_SIZE.0 as i32, TEXTURE_SIZE.1 as i32);
+    let rules = preset_rules(rule_index).ok();
+    let mut texels = vec![Vec4::W; (width * height) as usize];
+    for (pattern, seed) in placed {
+        let channel = seed.channel.min(2);
+        let states = rules.map_or(2, |rules| rules[channel].states);
+        if let (Some(text), Some(rules)) = (&pattern.rule, rules) {
+            if text.parse::<CellRule>().ok() != Some(rules[channel]) {
+                warn!(
+                    "pattern {} expects rule {}",
+                    pattern.name.as_deref().unwrap_or(seed.path),
+                    text
+                );
+            }
+        }
+        let corner = IVec2::new(width, height) / 2 + seed.offset - pattern.size.as_ivec2() / 2;
+        for (pos, state) in &pattern.cells {
+            let texel = corner + pos.as_ivec2();
+            let is_inside =
+                texel.cmpge(IVec2::ZERO).all() && texel.cmplt(IVec2::new(width, height)).all();
+            if !is_inside || *state >= states {
+                continue;
+            }
+            let value = 1.0 - (*state - 1) as f32 / (states - 1) as f32;
+            texels[(texel.y * width + texel.x) as usize][channel] = value;
+        }
+    }
+    texels
+        .iter()
+        .flat_map(|texel| texel.to_array())
+        .flat_map(f32::to_le_bytes)
+        .collect()
+}
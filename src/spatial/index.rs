@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use kd_tree::{KdPoint, KdTree};
+
+#[derive(Clone, Copy, Debug)]
+pub struct IndexedPoint {
+    pub position: Vec3,
+    pub entity: Entity,
+}
+
+impl KdPoint for IndexedPoint {
+    type Scalar = f32;
+    type Dim = typenum::U3;
+    fn at(&self, k: usize) -> f32 {
+        self.position[k]
+    }
+}
+
+/// Neighbour queries over the positions of indexed entities
+#[derive(Resource)]
+pub struct SpatialIndex {
+    tree: KdTree<IndexedPoint>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self::from_points(Vec::new())
+    }
+}
+
+impl SpatialIndex {
+    pub fn from_points(points: Vec<IndexedPoint>) -> Self {
+        Self {
+            tree: KdTree::build_by_ordered_float(points),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Closest entities sorted by increasing distance, the nearest neighbour for k = 1
+    pub fn k_nearest(&self, position: Vec3, k: usize) -> Vec<(Entity, f32)> {
+        self.tree
+            .nearests(&position.to_array(), k)
+            .into_iter()
+            .map(|found| (found.item.entity, found.squared_distance.sqrt()))
+            .collect()
+    }
+
+    /// Entities closer than radius, in no particular order
+    pub fn within_radius(&self, position: Vec3, radius: f32) -> Vec<Entity> {
+        self.tree
+            .within_radius(&position.to_array(), radius)
+            .into_iter()
+            .map(|point| point.entity)
+            .collect()
+    }
+}
@@ -0,0 +1,45 @@
+mod index;
+
+use bevy::prelude::*;
+
+pub use index::{IndexedPoint, SpatialIndex};
+
+/// Tags entities tracked by the [`SpatialIndex`]
+#[derive(Component, Default)]
+pub struct Indexed;
+
+//////////////////////////////////////////////////////////////////////
+
+/// Keeps the [`SpatialIndex`] in sync with the indexed entities
+pub struct SpatialIndexPlugin;
+
+impl Plugin for SpatialIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialIndex>();
+        app.add_systems(
+            PostUpdate,
+            rebuild_spatial_index.after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Rebuilds the tree only when an indexed entity moved, appeared or disappeared
+fn rebuild_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    indexed: Query<(Entity, &GlobalTransform), With<Indexed>>,
+    changed: Query<(), (With<Indexed>, Changed<GlobalTransform>)>,
+    mut removed: RemovedComponents<Indexed>,
+) {
+    let is_dirty = removed.read().count() > 0 || !changed.is_empty();
+    if !is_dirty && index.len() == indexed.iter().len() {
+        return;
+    }
+    let points = indexed
+        .iter()
+        .map(|(entity, transform)| IndexedPoint {
+            position: transform.translation(),
+            entity,
+        })
+        .collect();
+    *index = SpatialIndex::from_points(points);
+}
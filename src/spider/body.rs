@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+
+/// How the body follows the ground under its planted feet
+#[derive(Component, Clone)]
+pub struct BodyAdaptation {
+    pub ride_height: f32,      // m, above the fitted plane
+    pub height_stiffness: f32, // 1 / s
+    pub tilt_stiffness: f32,   // 1 / s
+}
+
+impl Default for BodyAdaptation {
+    fn default() -> Self {
+        Self {
+            ride_height: 0.0,
+            height_stiffness: 6.0,
+            tilt_stiffness: 4.0,
+        }
+    }
+}
+
+impl BodyAdaptation {
+    /// Exponential smoothing factors for height and tilt over dt
+    pub fn alphas(&self, dt: f32) -> (f32, f32) {
+        let alpha_height = 1.0 - (-self.height_stiffness * dt).exp();
+        let alpha_tilt = 1.0 - (-self.tilt_stiffness * dt).exp();
+        (alpha_height, alpha_tilt)
+    }
+}
+
+/// Least squares plane y = a x + b z + c through the points
+pub struct GroundPlane {
+    coeffs: Vec3, // (a, b, c)
+}
+
+impl GroundPlane {
+    pub fn fit(points: &[Vec3]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+        let mut ata = Mat3::ZERO;
+        let mut atb = Vec3::ZERO;
+        for point in points {
+            let row = Vec3::new(point.x, point.z, 1.0);
+            ata += Mat3::from_cols(row * row.x, row * row.y, row * row.z);
+            atb += row * point.y;
+        }
+        if ata.determinant().abs() < 1e-6 {
+            return None;
+        }
+        let coeffs = ata.inverse() * atb;
+        Some(Self { coeffs })
+    }
+
+    pub fn height_at(&self, pos: Vec2) -> f32 {
+        self.coeffs.x * pos.x + self.coeffs.y * pos.y + self.coeffs.z
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        Vec3::new(-self.coeffs.x, 1.0, -self.coeffs.y).normalize()
+    }
+}
@@ -0,0 +1,176 @@
+use super::data::{SpiderData, VehicleCommand};
+use super::navigation::{Steering, seek, stop};
+use crate::players::PlayerRegistry;
+use crate::spatial::SpatialIndex;
+
+use bevy::prelude::*;
+
+const WAYPOINT_RADIUS: f32 = 3.0; // m, patrol and wander goals are reached within this distance
+const CRUISE_LOOKAHEAD: f32 = 8.0; // m, added to the distance of goals the spider drives through
+const YIELD_RADIUS: f32 = 6.0; // m, brains brake for spiders closer than this in front of them
+const PERCEPTION_COUNT: usize = 4; // nearest indexed entities a brain looks at
+
+/// Vehicles driven by the input source assigned to a player slot,
+/// their brain, if any, takes over while the slot is free
+#[derive(Component)]
+pub struct HumanDriver {
+    pub slot: usize,
+}
+
+#[derive(Clone, Debug)]
+pub enum Behavior {
+    Patrol {
+        waypoints: Vec<Vec2>,
+        index: usize,
+    },
+    Follow {
+        target: Entity,
+        distance: f32,
+    },
+    Flee {
+        from: Vec2,
+        radius: f32,
+    },
+    Wander {
+        center: Vec2,
+        radius: f32,
+        goal: Option<Vec2>,
+    },
+}
+
+/// Vehicles driven by a built-in behavior, producing the same commands as a player
+#[derive(Component, Clone, Debug)]
+pub struct Brain {
+    pub behavior: Behavior,
+    rng: u32,
+}
+
+impl Brain {
+    pub fn new(behavior: Behavior) -> Self {
+        Self {
+            behavior,
+            rng: 0x9e3779b9,
+        }
+    }
+
+    fn think(&mut self, pos: Vec2, velocity: Vec2, angle: f32, target: Option<Vec2>) -> Steering {
+        let Self { behavior, rng } = self;
+        let (goal, remaining) = match behavior {
+            Behavior::Patrol { waypoints, index } => {
+                if waypoints.is_empty() {
+                    return stop(velocity, angle);
+                }
+                if pos.distance(waypoints[*index]) < WAYPOINT_RADIUS {
+                    *index = (*index + 1) % waypoints.len();
+                }
+                let goal = waypoints[*index];
+                (goal, pos.distance(goal) + CRUISE_LOOKAHEAD)
+            }
+            Behavior::Follow { distance, .. } => {
+                let Some(goal) = target else {
+                    return stop(velocity, angle);
+                };
+                let remaining = pos.distance(goal) - *distance;
+                if remaining <= 0.0 {
+                    return stop(velocity, angle);
+                }
+                (goal, remaining)
+            }
+            Behavior::Flee { from, radius } => {
+                let delta = pos - *from;
+                let remaining = *radius - delta.length();
+                if remaining <= 0.0 {
+                    return stop(velocity, angle);
+                }
+                let away = delta.try_normalize().unwrap_or(Vec2::X);
+                (pos + away * *radius, remaining + CRUISE_LOOKAHEAD)
+            }
+            Behavior::Wander {
+                center,
+                radius,
+                goal,
+            } => {
+                let is_reached = goal.is_none_or(|goal| pos.distance(goal) < WAYPOINT_RADIUS);
+                if is_reached {
+                    let angle = random(rng) * std::f32::consts::TAU;
+                    let distance = random(rng).sqrt() * *radius;
+                    *goal = Some(*center + Vec2::from_angle(angle) * distance);
+                }
+                let goal = goal.unwrap();
+                (goal, pos.distance(goal))
+            }
+        };
+        seek(pos, velocity, angle, goal, remaining)
+    }
+}
+
+/// Cheap xorshift in [0, 1)
+fn random(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state as f32 / (u32::MAX as f32 + 1.0)
+}
+
+/// Spider in front within the yield radius, the older spider keeps the right of way
+fn is_blocked(
+    index: &SpatialIndex,
+    spiders: &Query<&SpiderData>,
+    entity: Entity,
+    vehicle: &SpiderData,
+) -> bool {
+    let pos = vehicle.position_current;
+    let heading = Vec2::from_angle(-vehicle.angle_current);
+    let position = Vec3::new(pos.x, vehicle.height_current, pos.y);
+    index
+        .k_nearest(position, PERCEPTION_COUNT)
+        .into_iter()
+        .filter(|(other, distance)| *other < entity && *distance < YIELD_RADIUS)
+        .filter_map(|(other, _)| spiders.get(other).ok())
+        .any(|other| (other.position_current - pos).dot(heading) > 0.0)
+}
+
+pub fn update_brains(
+    mut vehicles: Query<(
+        Entity,
+        &SpiderData,
+        &mut VehicleCommand,
+        &mut Brain,
+        Option<&HumanDriver>,
+    )>,
+    targets: Query<&SpiderData>,
+    index: Res<SpatialIndex>,
+    registry: Res<PlayerRegistry>,
+    time: Res<Time<Fixed>>,
+) {
+    let dt = time.timestep().as_secs_f32();
+    for (entity, vehicle, mut command, mut brain, driver) in &mut vehicles {
+        if driver.is_some_and(|driver| registry.source(driver.slot).is_some()) {
+            continue;
+        }
+        let target = match brain.behavior {
+            Behavior::Follow { target, .. } => targets
+                .get(target)
+                .ok()
+                .map(|target| target.position_current),
+            _ => None,
+        };
+        let pos = vehicle.position_current;
+        let velocity = (pos - vehicle.position_previous) / dt;
+        let steering = brain.think(pos, velocity, vehicle.angle_current, target);
+        let steering = if is_blocked(&index, &targets, entity, vehicle) {
+            Steering {
+                turn: steering.turn,
+                ..stop(velocity, vehicle.angle_current)
+            }
+        } else {
+            steering
+        };
+        *command = VehicleCommand {
+            thrust: steering.thrust,
+            brake: steering.brake,
+            turn: steering.turn,
+            ..default()
+        };
+    }
+}
@@ -0,0 +1,119 @@
+use super::terrain;
+
+use bevy::prelude::*;
+
+/// Local frame of a spider crawling on an arbitrary surface
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceFrame {
+    pub position: Vec3, // contact point under the body
+    pub up: Vec3,       // surface normal
+    pub forward: Vec3,  // heading, tangent to the surface
+}
+
+const LOOK_AHEAD: f32 = 1.5; // m, distance at which the body starts tilting onto a wall in front
+const CLEARANCE: f32 = 0.5; // m, height of the forward probe
+const PROBE_HEIGHT: f32 = 1.0; // m
+const PROBE_DEPTH: f32 = 1.5; // m
+const EDGE_PROBE: f32 = 1.0; // m, depth of the probe wrapping around convex edges
+const STEEP_SLOPE: f32 = 0.7; // cos of the angle above which a surface becomes a wall
+
+impl SurfaceFrame {
+    pub fn from_position_and_angle(pos: Vec3, angle: f32) -> Self {
+        Self {
+            position: pos,
+            up: Vec3::Y,
+            forward: Quat::from_axis_angle(Vec3::Y, angle) * Vec3::X,
+        }
+    }
+
+    /// Body rotation, the model faces local +X
+    pub fn rotation(&self) -> Quat {
+        let side = self.forward.cross(self.up);
+        Quat::from_mat3(&Mat3::from_cols(self.forward, self.up, side))
+    }
+
+    /// Yaw of the heading once back on the floor
+    pub fn angle(&self) -> f32 {
+        -self.forward.xz().to_angle()
+    }
+
+    pub fn turn(&mut self, angle: f32) {
+        self.forward = Quat::from_axis_angle(self.up, angle) * self.forward;
+    }
+
+    /// Tilts the frame so that up matches the normal, carrying the heading along
+    fn reorient(&mut self, contact: terrain::Contact) {
+        let rotation = Quat::from_rotation_arc(self.up, contact.normal);
+        let forward = (rotation * self.forward).reject_from(contact.normal);
+        self.position = contact.point;
+        self.up = contact.normal;
+        self.forward = forward.try_normalize().unwrap_or(self.forward);
+    }
+
+    /// Moves towards the contact by distance at most, tilting up towards the normal
+    /// in proportion so that both frames match once the contact is reached
+    fn approach(&mut self, contact: terrain::Contact, distance: f32) {
+        let delta = contact.point - self.position;
+        let remaining = delta.length();
+        if remaining <= distance {
+            self.reorient(contact);
+            return;
+        }
+        let ratio = distance / remaining;
+        let rotation =
+            Quat::IDENTITY.slerp(Quat::from_rotation_arc(self.up, contact.normal), ratio);
+        let up = (rotation * self.up).normalize();
+        let forward = (rotation * self.forward).reject_from(up);
+        self.position += delta * ratio;
+        self.up = up;
+        self.forward = forward.try_normalize().unwrap_or(self.forward);
+    }
+
+    /// Moves along the surface by step, returns false when the surface is lost
+    pub fn crawl(
+        &mut self,
+        step: Vec3,
+        ray_cast: &mut MeshRayCast,
+        filter: &impl Fn(Entity) -> bool,
+    ) -> bool {
+        let step = step.reject_from(self.up);
+        let distance = step.length();
+
+        // concave edge, climb the wall in front
+        if distance > 0.0 {
+            let origin = self.position + self.up * CLEARANCE;
+            let contact = terrain::cast(ray_cast, filter, origin, step, distance + LOOK_AHEAD);
+            if let Some(contact) = contact {
+                if contact.normal.dot(self.up) < STEEP_SLOPE {
+                    self.approach(contact, distance);
+                    return true;
+                }
+            }
+        }
+
+        // stick to the surface under the target
+        let target = self.position + step;
+        let origin = target + self.up * PROBE_HEIGHT;
+        let contact = terrain::cast(
+            ray_cast,
+            filter,
+            origin,
+            -self.up,
+            PROBE_HEIGHT + PROBE_DEPTH,
+        );
+        if let Some(contact) = contact {
+            self.reorient(contact);
+            return true;
+        }
+
+        // convex edge, wrap around the face we just walked off
+        let origin = target - self.up * EDGE_PROBE;
+        let contact = terrain::cast(ray_cast, filter, origin, -step, distance + EDGE_PROBE);
+        if let Some(contact) = contact {
+            self.reorient(contact);
+            return true;
+        }
+
+        false
+    }
+}
@@ -1,3 +1,5 @@
+use super::crawl::SurfaceFrame;
+
 use bevy::prelude::*;
 
 #[derive(Component, Clone)]
@@ -7,20 +9,55 @@ pub struct SpiderData {
     pub position_previous: Vec2,
     pub position_current: Vec2,
     angle_initial: f32,
+    pub angle_previous: f32,
     pub angle_current: f32,
     pub is_target_captured: bool,
+    pub ground_height: f32,
+    pub ground_normal: Vec3,
+    pub height_current: f32,
+    pub tilt_current: Quat,
+    pub surface: Option<SurfaceFrame>,
+    pub pose_previous: Transform, // body pose at the previous physics tick
+    pub pose_current: Transform,  // body pose at the current physics tick
+}
+
+/// Controls applied to a vehicle at the next physics tick
+#[derive(Component, Clone, Default)]
+pub struct VehicleCommand {
+    pub thrust: f32,          // [0, 1]
+    pub brake: f32,           // [0, 1]
+    pub turn: f32,            // [-1, 1], positive turns left
+    pub target_move: Vec2,    // [-1, 1]^2, moves the capture target
+    pub toggle_capture: bool, // latched until consumed by a physics tick
+}
+
+/// Controls sampled every frame, latched into the command at the next physics tick
+#[derive(Component, Clone, Default)]
+pub struct VehicleInput(pub VehicleCommand);
+
+fn initial_pose(pos: Vec2, angle: f32) -> Transform {
+    Transform::from_xyz(pos.x, 0.0, pos.y).with_rotation(Quat::from_axis_angle(Vec3::Y, angle))
 }
 
 impl SpiderData {
     pub fn from_position_and_angle(pos: Vec2, angle: f32) -> Self {
+        let pose = initial_pose(pos, angle);
         Self {
             position_initial: pos,
             position_target: pos,
             position_previous: pos,
             position_current: pos,
             angle_initial: angle,
+            angle_previous: angle,
             angle_current: angle,
             is_target_captured: false,
+            ground_height: 0.0,
+            ground_normal: Vec3::Y,
+            height_current: 0.0,
+            tilt_current: Quat::IDENTITY,
+            surface: None,
+            pose_previous: pose,
+            pose_current: pose,
         }
     }
 
@@ -28,7 +65,38 @@ impl SpiderData {
         self.position_target = self.position_initial;
         self.position_previous = self.position_initial;
         self.position_current = self.position_initial;
+        self.angle_previous = self.angle_initial;
         self.angle_current = self.angle_initial;
         self.is_target_captured = false;
+        self.ground_height = 0.0;
+        self.ground_normal = Vec3::Y;
+        self.height_current = 0.0;
+        self.tilt_current = Quat::IDENTITY;
+        self.surface = None;
+        self.pose_previous = initial_pose(self.position_initial, self.angle_initial);
+        self.pose_current = self.pose_previous;
+    }
+
+    /// Starts crawling from the current floor position
+    pub fn attach(&mut self) {
+        let pos = self.position_current;
+        let pos = Vec3::new(pos.x, self.height_current, pos.y);
+        self.surface = Some(SurfaceFrame::from_position_and_angle(
+            pos,
+            self.angle_current,
+        ));
+    }
+
+    /// Drops back to the floor, below the last contact point
+    pub fn detach(&mut self) {
+        if let Some(surface) = self.surface.take() {
+            let pos = surface.position.xz();
+            self.position_previous = pos;
+            self.position_current = pos;
+            self.angle_previous = surface.angle();
+            self.angle_current = surface.angle();
+            self.height_current = surface.position.y;
+            self.tilt_current = Quat::IDENTITY;
+        }
     }
 }
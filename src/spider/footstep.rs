@@ -0,0 +1,146 @@
+use super::gait::LegKey;
+use super::terrain::SpiderPart;
+
+use bevy::audio::{SpatialScale, Volume};
+use bevy::prelude::*;
+
+const SOUND_STEP_PATH: &str = "sounds/breakout_collision.ogg";
+const DUST_COUNT: usize = 6;
+const DUST_LIFETIME: f32 = 0.6; // s
+const DUST_SPEED: f32 = 2.0; // m / s
+const DUST_DAMPING: f32 = 4.0; // 1 / s
+
+/// Sent when a swinging foot lands on the ground
+#[derive(Event, Clone, Debug)]
+pub struct FootPlanted {
+    pub spider: Entity,
+    pub leg: LegKey,
+    pub position: Vec3,
+    pub impact_speed: f32, // m / s
+}
+
+/// Opt-in step sounds and dust bursts driven by [`FootPlanted`] events
+pub struct FootstepPlugin;
+
+impl Plugin for FootstepPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, populate_footstep_assets);
+        app.add_systems(
+            Update,
+            (add_spatial_listener, play_footsteps, animate_dust).chain(),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct FootstepAssets {
+    sound: Handle<AudioSource>,
+    dust_mesh: Handle<Mesh>,
+    dust_material: Handle<StandardMaterial>,
+}
+
+#[derive(Component)]
+struct DustParticle {
+    velocity: Vec3,
+    age: f32,
+}
+
+fn populate_footstep_assets(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let sound = server.load(SOUND_STEP_PATH);
+    let dust_mesh = meshes.add(Sphere::new(0.15).mesh().ico(1).unwrap());
+    let dust_material = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.6, 0.55, 0.45, 0.6),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    commands.insert_resource(FootstepAssets {
+        sound,
+        dust_mesh,
+        dust_material,
+    });
+}
+
+fn add_spatial_listener(mut commands: Commands, cameras: Query<Entity, Added<Camera3d>>) {
+    for camera in &cameras {
+        commands.entity(camera).insert(SpatialListener::new(4.0));
+    }
+}
+
+/// Cheap xorshift, good enough for pitch and dust jitter
+fn jitter(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+fn play_footsteps(
+    mut commands: Commands,
+    mut events: EventReader<FootPlanted>,
+    assets: Res<FootstepAssets>,
+    mut rng: Local<u32>,
+) {
+    if *rng == 0 {
+        *rng = 0x9e3779b9;
+    }
+    for event in events.read() {
+        debug!(
+            "{:?} {} planted at {}",
+            event.spider, event.leg, event.position
+        );
+        let volume = (event.impact_speed / 10.0).clamp(0.1, 1.0);
+        let speed = 1.0 + 0.15 * jitter(&mut rng);
+        commands.spawn((
+            AudioPlayer::new(assets.sound.clone()),
+            PlaybackSettings::DESPAWN
+                .with_spatial(true)
+                .with_spatial_scale(SpatialScale::new(0.1))
+                .with_volume(Volume::Linear(volume))
+                .with_speed(speed),
+            Transform::from_translation(event.position),
+        ));
+
+        for _ in 0..DUST_COUNT {
+            let dir = Vec3::new(
+                jitter(&mut rng),
+                0.5 + jitter(&mut rng).abs(),
+                jitter(&mut rng),
+            );
+            commands.spawn((
+                SpiderPart,
+                DustParticle {
+                    velocity: dir.normalize_or_zero() * DUST_SPEED,
+                    age: 0.0,
+                },
+                Mesh3d(assets.dust_mesh.clone()),
+                MeshMaterial3d(assets.dust_material.clone()),
+                Transform::from_translation(event.position),
+            ));
+        }
+    }
+}
+
+fn animate_dust(
+    mut commands: Commands,
+    mut particles: Query<(Entity, &mut DustParticle, &mut Transform)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut particle, mut transform) in &mut particles {
+        particle.age += dt;
+        if particle.age > DUST_LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let velocity = particle.velocity;
+        transform.translation += velocity * dt;
+        particle.velocity *= (-DUST_DAMPING * dt).exp();
+        transform.scale = Vec3::splat(1.0 + 2.0 * particle.age / DUST_LIFETIME);
+    }
+}
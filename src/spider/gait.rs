@@ -0,0 +1,57 @@
+use super::rig::GaitDescription;
+
+use serde::Deserialize;
+
+pub type LegKey = String;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum Gait {
+    #[default]
+    Tripod,
+    Wave,
+    Ripple,
+}
+
+pub const GAITS: &[Gait] = &[Gait::Tripod, Gait::Wave, Gait::Ripple];
+
+#[derive(Default)]
+pub struct GaitScheduler {
+    pub gait: Gait,
+    gaits: Vec<GaitDescription>, // declared by the rig
+    phase: f32,                  // [0, 1) over a full gait cycle
+}
+
+impl GaitScheduler {
+    const BASE_FREQUENCY: f32 = 0.5; // cycle / s
+    const STRIDE_LENGTH: f32 = 2.0; // m / cycle
+
+    pub fn set_gaits(&mut self, gaits: Vec<GaitDescription>) {
+        self.gaits = gaits;
+    }
+
+    /// Legs lifting together, in stepping order
+    fn groups(&self) -> Option<&[Vec<LegKey>]> {
+        self.gaits
+            .iter()
+            .find(|description| description.gait == self.gait)
+            .map(|description| description.groups.as_slice())
+            .filter(|groups| !groups.is_empty())
+    }
+
+    /// Advances the cycle, faster spiders cycle faster
+    pub fn advance(&mut self, speed: f32, dt: f32) {
+        let frequency = Self::BASE_FREQUENCY + speed / Self::STRIDE_LENGTH;
+        self.phase += frequency * dt;
+        self.phase = self.phase.fract();
+    }
+
+    /// Legs can step freely when the rig does not declare the current gait
+    pub fn can_step(&self, key: &LegKey) -> bool {
+        let Some(groups) = self.groups() else {
+            return true;
+        };
+        let index = (self.phase * groups.len() as f32) as usize;
+        let index = index.min(groups.len() - 1);
+        groups[index].contains(key)
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+
+/// Analytic two-bone chain hip -> knee -> foot
+#[derive(Clone, Copy, Debug)]
+pub struct TwoBoneChain {
+    pub upper_length: f32, // m, hip to knee
+    pub lower_length: f32, // m, knee to foot
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TwoBoneSolution {
+    pub knee: Vec3,
+    pub foot: Vec3,
+    pub bend: Vec3, // unit vector orthogonal to hip -> foot, pointing towards the knee
+}
+
+impl TwoBoneChain {
+    const REACH_MARGIN: f32 = 1e-3;
+
+    pub fn max_reach(&self) -> f32 {
+        self.upper_length + self.lower_length
+    }
+
+    pub fn min_reach(&self) -> f32 {
+        (self.upper_length - self.lower_length).abs()
+    }
+
+    /// Places the knee in the plane spanned by hip, target and pole.
+    /// The foot is clamped to the reachable shell around the hip.
+    pub fn solve(&self, hip: Vec3, target: Vec3, pole: Vec3) -> TwoBoneSolution {
+        assert!(self.upper_length > 0.0);
+        assert!(self.lower_length > 0.0);
+
+        let delta = target - hip;
+        let dir = delta.try_normalize().unwrap_or(Vec3::NEG_Y);
+        let distance = delta.length();
+        let reach = distance.clamp(
+            self.min_reach() + Self::REACH_MARGIN,
+            self.max_reach() - Self::REACH_MARGIN,
+        );
+
+        // bend direction is the pole direction with the hip -> foot component removed
+        let bend = (pole - hip).reject_from_normalized(dir);
+        let bend = bend
+            .try_normalize()
+            .unwrap_or_else(|| dir.any_orthonormal_vector());
+
+        // law of cosines at the hip
+        let upper_sq = self.upper_length * self.upper_length;
+        let lower_sq = self.lower_length * self.lower_length;
+        let cos_hip = (upper_sq + reach * reach - lower_sq) / (2.0 * self.upper_length * reach);
+        let cos_hip = cos_hip.clamp(-1.0, 1.0);
+        let sin_hip = (1.0 - cos_hip * cos_hip).sqrt();
+
+        let foot = hip + dir * reach;
+        let knee = hip + (dir * cos_hip + bend * sin_hip) * self.upper_length;
+
+        TwoBoneSolution { knee, foot, bend }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN: TwoBoneChain = TwoBoneChain {
+        upper_length: 1.5,
+        lower_length: 3.0,
+    };
+
+    fn assert_lengths(hip: Vec3, solution: &TwoBoneSolution) {
+        assert!((solution.knee.distance(hip) - CHAIN.upper_length).abs() < 1e-4);
+        assert!((solution.foot.distance(solution.knee) - CHAIN.lower_length).abs() < 1e-4);
+        assert!(solution.bend.is_normalized());
+    }
+
+    #[test]
+    fn reachable_target() {
+        let hip = Vec3::new(1.0, 2.0, 3.0);
+        let target = hip + Vec3::new(3.0, -1.0, 0.0);
+        let pole = hip + Vec3::Y * 5.0;
+        let solution = CHAIN.solve(hip, target, pole);
+        assert_lengths(hip, &solution);
+        assert!(solution.foot.distance(target) < 1e-4);
+        // the knee bends towards the pole
+        assert!((solution.knee - hip).dot(Vec3::Y) > 0.0);
+        assert!(solution.bend.dot(target - hip).abs() < 1e-4);
+    }
+
+    #[test]
+    fn target_out_of_reach() {
+        let hip = Vec3::ZERO;
+        let target = Vec3::X * 10.0;
+        let solution = CHAIN.solve(hip, target, Vec3::Y);
+        assert_lengths(hip, &solution);
+        let reach = CHAIN.max_reach() - TwoBoneChain::REACH_MARGIN;
+        assert!(solution.foot.distance(Vec3::X * reach) < 1e-4);
+    }
+
+    #[test]
+    fn degenerate_pole() {
+        let hip = Vec3::ZERO;
+        let target = Vec3::X * 3.0;
+        // pole on the hip -> target line, no bend plane
+        let solution = CHAIN.solve(hip, target, Vec3::X * 5.0);
+        assert_lengths(hip, &solution);
+        assert!(solution.foot.distance(target) < 1e-4);
+        assert!(solution.bend.dot(Vec3::X).abs() < 1e-4);
+
+        // target on the hip, the foot hangs below at the minimal reach
+        let solution = CHAIN.solve(hip, hip, hip);
+        assert_lengths(hip, &solution);
+        assert!(solution.foot.normalize().distance(Vec3::NEG_Y) < 1e-4);
+    }
+}
@@ -0,0 +1,41 @@
+use serde::Deserialize;
+
+/// Animation clip roles, mapped to named glTF clips by the rig
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub enum Locomotion {
+    Idle,
+    Walk,
+    Run,
+}
+
+const WALK_SPEED: f32 = 4.0; // m / s, walk clip plays at nominal speed
+const RUN_SPEED: f32 = 16.0; // m / s, run clip plays at nominal speed
+const TURN_RADIUS: f32 = 2.0; // m, turning in place reads as walking at this radius
+
+pub struct LocomotionBlend {
+    pub weight: f32,
+    pub speed: f32, // playback speed
+}
+
+impl Locomotion {
+    /// Blend weight and playback speed of the clip for the given motion
+    pub fn blend(&self, speed: f32, turn_rate: f32) -> LocomotionBlend {
+        let speed = speed + turn_rate.abs() * TURN_RADIUS;
+        let moving = (speed / WALK_SPEED).clamp(0.0, 1.0);
+        let running = ((speed - WALK_SPEED) / (RUN_SPEED - WALK_SPEED)).clamp(0.0, 1.0);
+        match self {
+            Locomotion::Idle => LocomotionBlend {
+                weight: 1.0 - moving,
+                speed: 1.0,
+            },
+            Locomotion::Walk => LocomotionBlend {
+                weight: moving * (1.0 - running),
+                speed: (speed / WALK_SPEED).max(0.25),
+            },
+            Locomotion::Run => LocomotionBlend {
+                weight: moving * running,
+                speed: (speed / RUN_SPEED).max(0.25),
+            },
+        }
+    }
+}
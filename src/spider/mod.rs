@@ -1,12 +1,44 @@
+mod body;
+mod brain;
+mod crawl;
 mod data;
+mod footstep;
+mod gait;
+mod ik;
+mod locomotion;
+mod navigation;
 mod physics;
-
+mod profile;
+mod rig;
+mod swing;
+mod targeting;
+mod terrain;
+
+use super::actions::{Action, ActionState};
+use super::collision::Footprint;
 use super::global_state::GlobalState;
+use super::players::PlayerRegistry;
+use super::simu::{BrushPattern, SimuBrush, SimuReadback};
+use super::spatial::Indexed;
 use super::ui::UiState;
 use bevy::math::NormedVectorSpace;
-use data::SpiderData;
+use body::{BodyAdaptation, GroundPlane};
+use brain::{Behavior, Brain, HumanDriver};
+use data::{SpiderData, VehicleCommand, VehicleInput};
+use footstep::FootPlanted;
+use gait::{GAITS, GaitScheduler, LegKey};
+use ik::TwoBoneChain;
+use locomotion::Locomotion;
+use navigation::NavPath;
 use physics::lift;
-
+use profile::{SpiderProfile, VehicleProfile, VehicleProfileLoader};
+use rig::{SpiderRig, SpiderRigLoader};
+use swing::{LegState, Swing};
+use targeting::TargetQueue;
+use terrain::SpiderPart;
+
+use bevy::asset::LoadState;
+use bevy::gltf::Gltf;
 use bevy::scene::SceneInstanceReady;
 
 use std::collections::BTreeMap;
@@ -16,23 +48,64 @@ use bevy::prelude::*;
 
 use std::f32::consts::PI;
 
-const MODEL_SPIDER_PATH: &str = "models/tachikoma.glb";
+pub use footstep::FootstepPlugin;
+
 // const MODEL_SPIDER_SCALE: f32 = 1.0;
 
+/// Rigged model a spider is spawned from
+#[derive(Clone, Copy, Debug)]
+pub struct SpiderModel {
+    pub scene_path: &'static str,
+    pub rig_path: &'static str,
+}
+
+pub const MODEL_TACHIKOMA: SpiderModel = SpiderModel {
+    scene_path: "models/tachikoma.glb",
+    rig_path: "rigs/tachikoma.rig.ron",
+};
+
+pub const PROFILE_DEFAULT: &str = "profiles/default.vehicle.ron";
+pub const PROFILE_NIMBLE: &str = "profiles/nimble.vehicle.ron";
+
 //////////////////////////////////////////////////////////////////////
 
-pub struct SpiderPlugin;
+pub struct SpiderPlugin {
+    pub physics_tick_hz: f64, // vehicle physics runs at this fixed rate, independently of the frame rate
+}
+
+impl Default for SpiderPlugin {
+    fn default() -> Self {
+        Self {
+            physics_tick_hz: 60.0,
+        }
+    }
+}
 
 impl Plugin for SpiderPlugin {
     fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(self.physics_tick_hz));
+        app.init_asset::<SpiderRig>();
+        app.init_asset_loader::<SpiderRigLoader>();
+        app.init_asset::<VehicleProfile>();
+        app.init_asset_loader::<VehicleProfileLoader>();
+        app.add_event::<FootPlanted>();
         app.add_systems(Startup, (populate_spider).chain());
         app.add_systems(
             Update,
             (
+                populate_legs,
+                play_animation,
+                profile::report_profile_changes,
                 reset_vehicle_positions,
-                physics::update_vehicle_physics,
+                toggle_crawl_mode,
+                update_gait_selection,
+                physics::read_vehicle_inputs,
+                targeting::pick_targets,
+                physics::interpolate_vehicle_transforms,
+                update_animation_weights,
                 update_spider_legs,
                 display_gizmos,
+                targeting::display_target_markers,
                 // collision::bounce_and_resolve_checkpoints,
                 // update_statuses,
                 // update_boards_and_cups,
@@ -40,66 +113,174 @@ impl Plugin for SpiderPlugin {
                 .chain()
                 .run_if(in_state(GlobalState::Ready)),
         );
+        app.add_systems(
+            FixedUpdate,
+            (
+                physics::latch_vehicle_inputs,
+                targeting::advance_target_queue,
+                brain::update_brains,
+                navigation::plan_navigation_paths,
+                physics::update_vehicle_physics,
+                step_spider_legs,
+            )
+                .chain()
+                .run_if(in_state(GlobalState::Ready)),
+        );
     }
 }
 
 //////////////////////////////////////////////////////////////////////
 
-fn reset_vehicle_positions(
-    mut vehicles: Query<&mut SpiderData>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-) {
-    if keyboard.just_pressed(KeyCode::KeyR) {
+fn reset_vehicle_positions(mut vehicles: Query<&mut SpiderData>, actions: Res<ActionState>) {
+    if actions.any.just_pressed(Action::Reset) {
         for mut vehicle in &mut vehicles {
             vehicle.reset();
         }
     }
 }
 
+/// The crawl action toggles crawling for the spider of the player pressing it
+fn toggle_crawl_mode(
+    mut vehicles: Query<(&mut SpiderData, &HumanDriver)>,
+    registry: Res<PlayerRegistry>,
+    actions: Res<ActionState>,
+) {
+    for (mut vehicle, driver) in &mut vehicles {
+        let is_toggled = registry
+            .source(driver.slot)
+            .and_then(|source| actions.source(source))
+            .is_some_and(|actions| actions.just_pressed(Action::ToggleCrawl));
+        if !is_toggled {
+            continue;
+        }
+        if vehicle.surface.is_some() {
+            info!("crawl mode off");
+            vehicle.detach();
+        } else {
+            info!("crawl mode on");
+            vehicle.attach();
+        }
+    }
+}
+
 //////////////////////////////////////////////////////////////////////
 
 #[derive(Clone, Debug)]
 struct SpiderLeg {
     parent: Entity,
     marker: Entity,
+    knee: Entity,
     entity: Entity,
+    chain: TwoBoneChain,
+    hip_offset: Transform, // hip in the body frame, at rest
+    rest_offset: Vec3,
+    pole: Option<Vec3>, // knee pull direction in the body frame
+    foot: Vec3,         // stepped at the physics rate
+    state: LegState,
+    normal: Vec3,
 }
 
 #[derive(Component)]
 struct SpiderAnimation {
-    graph: Handle<AnimationGraph>,
-    index: AnimationNodeIndex,
-    legs: BTreeMap<(String, String), SpiderLeg>,
+    gltf: Handle<Gltf>,
+    player: Option<Entity>,
+    clips: BTreeMap<Locomotion, AnimationNodeIndex>,
+    rig: Handle<SpiderRig>,
+    legs: BTreeMap<LegKey, SpiderLeg>,
+    gait: GaitScheduler,
 }
 
-fn populate_spider(
-    server: Res<AssetServer>,
-    mut commands: Commands,
-    mut graphs: ResMut<Assets<AnimationGraph>>,
-) {
-    // animation from our example asset, which has an index of two.
-    let (graph, index) = AnimationGraph::from_clip(
-        server.load(GltfAssetLabel::Animation(0).from_asset(MODEL_SPIDER_PATH)),
-    );
-    let graph: Handle<AnimationGraph> = graphs.add(graph);
+fn populate_spider(server: Res<AssetServer>, mut commands: Commands) {
+    let mut spawn = |pos: Vec2, angle: f32, profile: &str| {
+        spawn_spider(&mut commands, &server, pos, angle, MODEL_TACHIKOMA, profile)
+    };
+
+    let player = spawn(Vec2::ZERO, -PI / 2.0, PROFILE_DEFAULT);
+    let follower = spawn(Vec2::new(-15.0, 10.0), 0.0, PROFILE_NIMBLE);
+    let patroller = spawn(Vec2::new(30.0, -30.0), PI, PROFILE_DEFAULT);
+    let wanderer = spawn(Vec2::new(-30.0, -30.0), 0.0, PROFILE_NIMBLE);
+    let coward = spawn(Vec2::new(-6.0, -12.0), 0.0, PROFILE_NIMBLE);
+
+    commands.entity(player).insert(HumanDriver { slot: 0 });
+    // the second player can join with a gamepad, the follower behavior drives it meanwhile
+    commands.entity(follower).insert((
+        HumanDriver { slot: 1 },
+        SimuBrush::new(1.5, 1, BrushPattern::Random { density: 0.5 }),
+        Brain::new(Behavior::Follow {
+            target: player,
+            distance: 10.0,
+        }),
+    ));
+    commands.entity(patroller).insert((
+        SimuBrush::new(1.0, 2, BrushPattern::Checker),
+        Brain::new(Behavior::Patrol {
+            waypoints: vec![
+                Vec2::new(30.0, -30.0),
+                Vec2::new(30.0, 30.0),
+                Vec2::new(20.0, 30.0),
+                Vec2::new(20.0, -30.0),
+            ],
+            index: 1,
+        }),
+    ));
+    commands.entity(wanderer).insert((
+        SimuBrush::new(1.0, 0, BrushPattern::Solid),
+        Brain::new(Behavior::Wander {
+            center: Vec2::new(-25.0, -25.0),
+            radius: 15.0,
+            goal: None,
+        }),
+    ));
+    // keeps away from the tower
+    commands.entity(coward).insert(Brain::new(Behavior::Flee {
+        from: Vec2::new(0.0, -10.0),
+        radius: 15.0,
+    }));
+}
 
-    let scene: Handle<Scene> = server.load(GltfAssetLabel::Scene(0).from_asset(MODEL_SPIDER_PATH));
+/// Spawns a spider instance, legs are discovered once its scene is ready
+pub fn spawn_spider(
+    commands: &mut Commands,
+    server: &AssetServer,
+    pos: Vec2,
+    angle: f32,
+    model: SpiderModel,
+    profile_path: &str,
+) -> Entity {
+    // named animation clips are looked up in the root glTF asset
+    let gltf: Handle<Gltf> = server.load(model.scene_path);
+    let scene: Handle<Scene> = server.load(GltfAssetLabel::Scene(0).from_asset(model.scene_path));
+    let rig: Handle<SpiderRig> = server.load(model.rig_path);
+    let profile: Handle<VehicleProfile> = server.load(profile_path.to_owned());
 
     let mut scene = commands.spawn((
         SceneRoot(scene.clone()),
-        SpiderData::from_position_and_angle(Vec2::ZERO, -PI / 2.0),
+        SpiderData::from_position_and_angle(pos, angle),
+        VehicleCommand::default(),
+        VehicleInput::default(),
+        SpiderProfile(profile),
+        Footprint::default(),
+        Indexed,
+        NavPath::default(),
+        TargetQueue::default(),
+        SimuBrush::default(),
+        BodyAdaptation::default(),
         SpiderAnimation {
-            graph,
-            index,
+            gltf,
+            player: None,
+            clips: BTreeMap::new(),
+            rig,
             legs: BTreeMap::new(),
+            gait: GaitScheduler::default(),
         },
-        Transform::IDENTITY,
+        Transform::from_translation(lift(pos)).with_rotation(Quat::from_axis_angle(Vec3::Y, angle)),
     ));
 
-    scene.observe(populate_legs);
-    scene.observe(play_animation);
+    scene.observe(mark_scene_ready);
     #[cfg(feature = "debug_gizmos")]
     scene.observe(add_reference_axis);
+
+    scene.id()
 }
 
 #[cfg(feature = "debug_gizmos")]
@@ -132,179 +313,588 @@ fn add_reference_axis(
 }
 
 fn play_animation(
-    trigger: Trigger<SceneInstanceReady>,
-    mut commands: Commands,
-    animations: Query<&SpiderAnimation>,
+    mut animations: Query<(Entity, &mut SpiderAnimation), With<PendingAnimation>>,
+    rigs: Res<Assets<SpiderRig>>,
+    gltfs: Res<Assets<Gltf>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
     children: Query<&Children>,
     mut players: Query<&mut AnimationPlayer>,
+    server: Res<AssetServer>,
+    mut commands: Commands,
 ) {
-    info!("** playing animation **");
+    for (target, mut animation) in &mut animations {
+        // wait for the clip mapping and the clips
+        let (Some(rig), Some(gltf)) = (rigs.get(&animation.rig), gltfs.get(&animation.gltf)) else {
+            let rig_state = server.load_state(&animation.rig);
+            let gltf_state = server.load_state(&animation.gltf);
+            for state in [rig_state, gltf_state] {
+                if let LoadState::Failed(err) = state {
+                    error!("spider animation failed to load: {err}");
+                    commands.entity(target).remove::<PendingAnimation>();
+                    break;
+                }
+            }
+            continue;
+        };
+        commands.entity(target).remove::<PendingAnimation>();
+
+        info!("** playing animation **");
+
+        // Every locomotion clip plays under a single blend node,
+        // their weights are driven by update_animation_weights
+        let mut graph = AnimationGraph::new();
+        let blend = graph.add_blend(1.0, graph.root);
+        let mut clips = BTreeMap::new();
+        let mut roles: BTreeMap<&str, Locomotion> = BTreeMap::new();
+        for (role, name) in &rig.clips {
+            // blending a clip with itself changes nothing, the first role keeps it
+            if let Some(other) = roles.get(name.as_str()) {
+                warn!(
+                    "spider rig: clip {} already plays as {:?}, {:?} ignored",
+                    name, other, role
+                );
+                continue;
+            }
+            match gltf.named_animations.get(name.as_str()) {
+                Some(clip) => {
+                    roles.insert(name.as_str(), *role);
+                    clips.insert(*role, graph.add_clip(clip.clone(), 1.0, blend));
+                }
+                None => error!(
+                    "spider rig: no clip named {} for {:?}, the model has {:?}",
+                    name,
+                    role,
+                    gltf.named_animations.keys().collect::<Vec<_>>(),
+                ),
+            }
+        }
+        let graph = graphs.add(graph);
 
-    // The entity we spawned in `setup_mesh_and_animation` is the trigger's target.
-    // Start by finding the AnimationToPlay component we added to that entity.
-    let target = trigger.target();
-    if let Ok(animation) = animations.get(target) {
         // The SceneRoot component will have spawned the scene as a hierarchy
         // of entities parented to our entity. Since the asset contained a skinned
         // mesh and animations, it will also have spawned an animation player
         // component. Search our entity's descendants to find the animation player.
         for child in children.iter_descendants(target) {
             if let Ok(mut player) = players.get_mut(child) {
-                // Tell the animation player to start the animation and keep
-                // repeating it.
-                //
-                // If you want to try stopping and switching animations, see the
-                // `animated_mesh_control.rs` example.
-                player.play(animation.index).repeat();
+                for node in clips.values() {
+                    player.play(*node).repeat();
+                }
 
                 // Add the animation graph. This only needs to be done once to
                 // connect the animation player to the mesh.
                 commands
                     .entity(child)
-                    .insert(AnimationGraphHandle(animation.graph.clone()));
+                    .insert(AnimationGraphHandle(graph.clone()));
+                animation.player = Some(child);
+            }
+        }
+
+        animation.clips = clips;
+    }
+}
+
+fn update_animation_weights(
+    animations: Query<(&SpiderAnimation, &SpiderData)>,
+    mut players: Query<&mut AnimationPlayer>,
+    time: Res<Time<Fixed>>,
+) {
+    // positions and angles are sampled once per physics tick
+    let dt = time.timestep().as_secs_f32();
+    if dt <= 0.0 {
+        return;
+    }
+    for (animation, vehicle) in &animations {
+        let Some(player) = animation.player else {
+            continue;
+        };
+        let mut player = players.get_mut(player).unwrap();
+        let speed = (vehicle.position_current - vehicle.position_previous).length() / dt;
+        let turn_rate = (vehicle.angle_current - vehicle.angle_previous) / dt;
+        // a lone clip plays at full weight, only its speed follows the motion
+        let is_blended = animation.clips.len() > 1;
+        for (role, node) in &animation.clips {
+            let blend = role.blend(speed, turn_rate);
+            let weight = if is_blended { blend.weight } else { 1.0 };
+            if let Some(active) = player.animation_mut(*node) {
+                active.set_weight(weight).set_speed(blend.speed);
             }
         }
     }
 }
 
-const SPIDER_LEG_LENGTH: f32 = 3.5;
 const SPIDER_STEP_LENGTH: f32 = 1.0;
 const SPIDER_STEP_LEAD: f32 = 0.25;
+const _: () = assert!(SPIDER_STEP_LEAD < SPIDER_STEP_LENGTH);
+
+/// Present between scene instantiation and leg discovery
+#[derive(Component)]
+struct PendingLegs;
+
+/// Present between scene instantiation and animation graph construction
+#[derive(Component)]
+struct PendingAnimation;
+
+fn mark_scene_ready(trigger: Trigger<SceneInstanceReady>, mut commands: Commands) {
+    commands
+        .entity(trigger.target())
+        .insert((PendingLegs, PendingAnimation));
+}
+
+/// Hip transform relative to the spider root, composed from the local transforms
+fn hip_offset(
+    hip: Entity,
+    root: Entity,
+    transforms: &Query<&Transform>,
+    parents: &Query<&ChildOf>,
+) -> Option<Transform> {
+    let mut offset = Transform::IDENTITY;
+    let mut node = hip;
+    while node != root {
+        offset = transforms.get(node).ok()?.mul_transform(offset);
+        node = parents.get(node).ok()?.parent();
+    }
+    Some(offset)
+}
 
 fn populate_legs(
-    trigger: Trigger<SceneInstanceReady>,
-    mut animation: Single<&mut SpiderAnimation>,
+    mut animations: Query<(Entity, &mut SpiderAnimation, &SpiderData), With<PendingLegs>>,
+    rigs: Res<Assets<SpiderRig>>,
+    server: Res<AssetServer>,
     children: Query<&Children>,
     names: Query<&Name>,
     parents: Query<&ChildOf>,
+    transforms: Query<&Transform>,
     mut commands: Commands,
     mut _meshes: ResMut<Assets<Mesh>>,
     mut _materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    info!("** populate legs **");
+    for (target, mut animation, vehicle) in &mut animations {
+        // wait for the rig description
+        let Some(rig) = rigs.get(&animation.rig) else {
+            if let LoadState::Failed(err) = server.load_state(&animation.rig) {
+                error!("spider rig failed to load: {err}");
+                commands.entity(target).remove::<PendingLegs>();
+            }
+            continue;
+        };
+        commands.entity(target).remove::<PendingLegs>();
 
-    let re = regex::Regex::new(r"^leg_(left|right)_(front|mid|back)$").unwrap();
-    let target = trigger.target();
+        info!("** populate legs **");
 
-    #[cfg(feature = "debug_gizmos")]
-    let block = {
-        let mesh = Cuboid::new(0.5, 0.5, SPIDER_LEG_LENGTH);
-        let material = StandardMaterial {
-            base_color: RED.into(),
-            emissive: RED.into(),
-            ..default()
-        };
-        let mesh = _meshes.add(mesh);
-        let material = _materials.add(material);
-
-        (
-            Mesh3d(mesh.clone()),
-            MeshMaterial3d(material.clone()),
-            Transform::from_xyz(0.0, 0.0, SPIDER_LEG_LENGTH / 2.0),
-        )
-    };
+        let problems = rig.validate(SPIDER_STEP_LENGTH);
+        if !problems.is_empty() {
+            for problem in problems {
+                error!("spider rig: {problem}");
+            }
+            continue;
+        }
 
-    for entity in children.iter_descendants(target) {
-        if let Ok(entity_name) = names.get(entity) {
-            if let Some(groups) = re.captures(entity_name) {
-                let key: (String, String) = (groups[1].into(), groups[2].into());
+        let mut bones: BTreeMap<String, Entity> = BTreeMap::new();
+        commands.entity(target).insert(SpiderPart);
+        for entity in children.iter_descendants(target) {
+            commands.entity(entity).insert(SpiderPart);
+            if let Ok(entity_name) = names.get(entity) {
+                bones.insert(entity_name.as_str().into(), entity);
+            }
+        }
 
-                #[cfg(feature = "debug_gizmos")]
-                let marker = {
-                    let mut marker = commands.spawn((Visibility::Visible, Transform::IDENTITY));
-                    marker.with_child(block.clone());
-                    marker.id()
+        let missing: Vec<&str> = rig
+            .legs
+            .iter()
+            .filter(|description| !bones.contains_key(&description.bone))
+            .map(|description| description.bone.as_str())
+            .collect();
+        if !missing.is_empty() {
+            error!(
+                "spider rig: missing leg bones {:?}, the scene has {:?}",
+                missing,
+                bones.keys().collect::<Vec<_>>(),
+            );
+            continue;
+        }
+
+        for description in &rig.legs {
+            let key: LegKey = description.bone.clone();
+            let entity = bones[&key];
+
+            let Ok(ChildOf(parent)) = parents.get(entity) else {
+                error!("spider rig: leg bone {} has no hip, skipped", key);
+                continue;
+            };
+            let parent = *parent;
+            let Ok(parent_name) = names.get(parent) else {
+                error!("spider rig: hip of leg bone {} has no name, skipped", key);
+                continue;
+            };
+            let Some(hip_offset) = hip_offset(parent, target, &transforms, &parents) else {
+                error!(
+                    "spider rig: hip of leg bone {} is not in the scene, skipped",
+                    key
+                );
+                continue;
+            };
+            let foot = vehicle
+                .pose_current
+                .mul_transform(hip_offset)
+                .transform_point(description.rest_offset);
+
+            #[cfg(feature = "debug_gizmos")]
+            let marker = {
+                let length = description.rest_offset.length();
+                let mesh = Cuboid::new(0.5, 0.5, length);
+                let material = StandardMaterial {
+                    base_color: RED.into(),
+                    emissive: RED.into(),
+                    ..default()
                 };
+                let block = (
+                    SpiderPart,
+                    Mesh3d(_meshes.add(mesh)),
+                    MeshMaterial3d(_materials.add(material)),
+                    Transform::from_xyz(0.0, 0.0, length / 2.0),
+                );
+                let mut marker =
+                    commands.spawn((SpiderPart, Visibility::Visible, Transform::IDENTITY));
+                marker.with_child(block);
+                marker.id()
+            };
+
+            #[cfg(not(feature = "debug_gizmos"))]
+            let marker = commands
+                .spawn((SpiderPart, Visibility::Visible, Transform::IDENTITY))
+                .id();
+
+            let knee = commands
+                .spawn((SpiderPart, Visibility::Visible, Transform::IDENTITY))
+                .id();
+
+            let value = SpiderLeg {
+                parent,
+                marker,
+                knee,
+                entity,
+                chain: TwoBoneChain {
+                    upper_length: description.upper_length,
+                    lower_length: description.lower_length,
+                },
+                hip_offset,
+                rest_offset: description.rest_offset,
+                pole: description.pole,
+                foot,
+                state: LegState::Planted,
+                normal: Vec3::Y,
+            };
+
+            info!("{} -> ({}, {:?})", key, parent_name, marker);
+
+            animation.legs.insert(key, value);
+        }
 
-                #[cfg(not(feature = "debug_gizmos"))]
-                let marker = commands
-                    .spawn((Visibility::Visible, Transform::IDENTITY))
-                    .id();
+        animation.gait.set_gaits(rig.gaits.clone());
 
-                let ChildOf(parent) = parents.get(entity).unwrap();
-                let parent = *parent;
+        // the leg mesh extends along its local -Z axis, it now hangs from the knee towards the foot
+        for leg in animation.legs.values() {
+            let mut leg_commands = commands.entity(leg.entity);
+            leg_commands.remove_parent_in_place();
+            leg_commands.set_parent_in_place(leg.knee);
+            leg_commands.insert(Transform::IDENTITY);
+        }
+    }
+}
 
-                let value = SpiderLeg {
-                    parent,
-                    marker,
-                    entity,
+fn update_gait_selection(ui_state: Res<UiState>, mut animations: Query<&mut SpiderAnimation>) {
+    let gait = GAITS[ui_state.gait_index];
+    for mut animation in &mut animations {
+        if animation.gait.gait != gait {
+            info!("gait {:?} -> {:?}", animation.gait.gait, gait);
+            animation.gait.gait = gait;
+        }
+    }
+}
+
+/// Runs in the fixed schedule after the physics, gait, swings and ground plane only depend on the tick
+fn step_spider_legs(
+    mut animations: Query<(Entity, &mut SpiderAnimation, &mut SpiderData)>,
+    mut ray_cast: MeshRayCast,
+    parts: Query<(), With<SpiderPart>>,
+    mut foot_planted: EventWriter<FootPlanted>,
+    time: Res<Time<Fixed>>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+    let is_terrain = |entity: Entity| !parts.contains(entity);
+    for (spider, mut animation, mut vehicle) in animations.iter_mut() {
+        let speed = (vehicle.position_current - vehicle.position_previous).length() / dt;
+        animation.gait.advance(speed, dt);
+
+        let animation = animation.as_mut();
+        let body_up = *vehicle.pose_current.up();
+        let mut planted_feet = Vec::new();
+        for (key, leg) in animation.legs.iter_mut() {
+            let hip = vehicle.pose_current.mul_transform(leg.hip_offset);
+            let pos = hip.transform_point(leg.rest_offset);
+
+            let delta = pos - leg.foot;
+            let is_planted = matches!(leg.state, LegState::Planted);
+            if is_planted && delta.norm() > SPIDER_STEP_LENGTH && animation.gait.can_step(key) {
+                let lead = delta.normalize() * SPIDER_STEP_LEAD;
+                let target = pos + lead;
+                let target = match terrain::project(&mut ray_cast, &is_terrain, target, body_up) {
+                    Some(contact) => {
+                        leg.normal = contact.normal;
+                        contact.point
+                    }
+                    None => target,
                 };
+                leg.state = LegState::Swinging(Swing::new(leg.foot, target));
+            }
 
-                let parent_name = names.get(parent).unwrap();
-                info!(
-                    "{:?} -> ({}, {:?}, {})",
-                    key.clone(),
-                    parent_name,
-                    marker,
-                    entity_name,
-                );
+            if let LegState::Swinging(swing) = &mut leg.state {
+                let before = leg.foot;
+                swing.advance(dt);
+                leg.foot = swing.position(body_up);
+                if swing.is_done() {
+                    leg.state = LegState::Planted;
+                    foot_planted.write(FootPlanted {
+                        spider,
+                        leg: key.clone(),
+                        position: leg.foot,
+                        impact_speed: (leg.foot - before).length() / dt,
+                    });
+                }
+            }
 
-                animation.legs.insert(key.clone(), value.clone());
+            if matches!(leg.state, LegState::Planted) {
+                planted_feet.push(leg.foot);
             }
         }
-    }
-
-    assert!(animation.legs.len() == 6);
 
-    for leg in animation.legs.values() {
-        let mut leg_commands = commands.entity(leg.entity);
-        leg_commands.remove_parent_in_place();
-        leg_commands.set_parent_in_place(leg.marker);
+        // keep the previous plane while too few feet are on the ground,
+        // crawling spiders follow the surface frame instead
+        if vehicle.surface.is_some() {
+            continue;
+        }
+        if let Some(plane) = GroundPlane::fit(&planted_feet) {
+            vehicle.ground_height = plane.height_at(vehicle.position_current);
+            vehicle.ground_normal = plane.normal();
+        }
     }
 }
 
+/// Places the feet stepped by the physics and solves the knees against the animated hips
 fn update_spider_legs(
-    animations: Query<&SpiderAnimation>,
+    animations: Query<(&SpiderAnimation, &GlobalTransform)>,
     global_transforms: Query<&GlobalTransform>,
     mut transforms: Query<&mut Transform>,
 ) {
-    assert!(SPIDER_STEP_LEAD < SPIDER_STEP_LENGTH);
-    for animation in animations.iter() {
+    for (animation, body_transform) in animations.iter() {
+        let body_pos = body_transform.translation();
+        let body_up = *body_transform.up();
         for leg in animation.legs.values() {
             let transform = global_transforms.get(leg.parent).unwrap();
-            let pos = transform.transform_point(Vec3::Y * SPIDER_LEG_LENGTH);
             let pos__ = transform.transform_point(Vec3::ZERO);
             assert!((pos__ - transform.translation()).norm() < 1e-5);
 
             let mut transform_ = transforms.get_mut(leg.marker).unwrap();
-            let pos_ = transform_.transform_point(Vec3::ZERO);
-            assert!((pos_ - transform_.translation).norm() < 1e-5);
-
-            let delta = pos - pos_;
-            if delta.norm() > SPIDER_STEP_LENGTH {
-                let lead = delta.normalize() * SPIDER_STEP_LEAD;
-                transform_.translation = pos + lead;
-            }
+            transform_.translation = leg.foot;
 
-            let delta = pos__ - pos_;
+            // feet face the hip and rest flat on the contact surface
+            let delta = pos__ - leg.foot;
             let angle = delta.zx().to_angle();
-            transform_.rotation = Quat::from_axis_angle(Vec3::Y, angle);
-
-            let mut transform__ = transforms.get_mut(leg.entity).unwrap();
-            transform__.translation.y = 0.5;
-            transform__.translation.z = 3.0;
+            transform_.rotation = Quat::from_rotation_arc(Vec3::Y, leg.normal)
+                * Quat::from_axis_angle(Vec3::Y, angle);
+
+            // knees point up and away from the body unless the rig tunes them
+            let pole_direction = match leg.pole {
+                Some(direction) => body_transform.rotation() * direction,
+                None => {
+                    let outward = (pos__ - body_pos).reject_from(body_up).normalize_or_zero();
+                    outward + 2.0 * body_up
+                }
+            };
+            let pole = pos__ + pole_direction * leg.chain.upper_length;
+            let solution = leg.chain.solve(pos__, leg.foot, pole);
+
+            let mut transform__ = transforms.get_mut(leg.knee).unwrap();
+            *transform__ =
+                Transform::from_translation(solution.knee).looking_at(solution.foot, solution.bend);
         }
     }
 }
 
 fn display_gizmos(
     ui_state: ResMut<UiState>,
-    vehicles_and_animations: Query<(&SpiderData, &SpiderAnimation)>,
+    vehicles_and_animations: Query<(&SpiderData, &SpiderAnimation, &NavPath)>,
     global_transforms: Query<&GlobalTransform>,
+    simu_readback: Res<SimuReadback>,
     mut gizmos: Gizmos,
 ) {
     if !ui_state.display_gizmos {
         return;
     }
-    for (vehicle, animation) in vehicles_and_animations.iter() {
+    for (vehicle, animation, path) in vehicles_and_animations.iter() {
         gizmos.cross(lift(vehicle.position_target), 5.0, BLUE_VIOLET);
-        gizmos.sphere(lift(vehicle.position_current), 2.0, GREEN_YELLOW);
+        // red over live cells of the simulation
+        let is_over_cells = simu_readback
+            .sample(lift(vehicle.position_current))
+            .is_some_and(|alive| alive.contains(&true));
+        gizmos.sphere(
+            lift(vehicle.position_current),
+            2.0,
+            if is_over_cells { RED } else { GREEN_YELLOW },
+        );
+        gizmos.linestrip(
+            std::iter::once(vehicle.position_current)
+                .chain(path.waypoints.iter().copied())
+                .map(lift),
+            BLUE_VIOLET,
+        );
         for leg in animation.legs.values() {
             let transform = global_transforms.get(leg.parent).unwrap();
-            let pos = transform.transform_point(Vec3::Y * SPIDER_LEG_LENGTH);
+            let pos = transform.transform_point(leg.rest_offset);
             let pos__ = transform.transform_point(Vec3::ZERO);
             assert!((pos__ - transform.translation()).norm() < 1e-5);
             gizmos.arrow(pos__, pos, WHITE);
+            let knee = global_transforms.get(leg.knee).unwrap().translation();
+            let foot = global_transforms.get(leg.marker).unwrap().translation();
+            gizmos.line(pos__, knee, ORANGE);
+            gizmos.line(knee, foot, ORANGE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::spatial::SpatialIndex;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use std::time::Duration;
+
+    const TICK_HZ: f64 = 64.0; // exact in nanoseconds, like both frame rates
+    const TICKS: usize = 256;
+
+    #[derive(Resource, Default)]
+    struct Replay {
+        states: Vec<(Vec2, f32, Transform, Vec<Vec3>)>,
+    }
+
+    /// Same commands at every tick whatever the frame rate, like a recorded input stream
+    fn replay_commands(mut commands: Query<&mut VehicleCommand>, mut tick: Local<usize>) {
+        for mut command in &mut commands {
+            *command = VehicleCommand {
+                thrust: if *tick < 128 { 1.0 } else { 0.0 },
+                brake: if *tick >= 192 { 1.0 } else { 0.0 },
+                turn: if *tick % 64 < 32 { 0.5 } else { -1.0 },
+                target_move: Vec2::new(1.0, -0.5),
+                toggle_capture: *tick == 160,
+            };
+        }
+        *tick += 1;
+    }
+
+    fn record_states(vehicles: Query<(&SpiderData, &SpiderAnimation)>, mut replay: ResMut<Replay>) {
+        for (vehicle, animation) in &vehicles {
+            replay.states.push((
+                vehicle.position_current,
+                vehicle.angle_current,
+                vehicle.pose_current,
+                animation.legs.values().map(|leg| leg.foot).collect(),
+            ));
+        }
+    }
+
+    fn run(frame_hz: f64) -> Replay {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / frame_hz,
+        )));
+        app.insert_resource(Time::<Fixed>::from_hz(TICK_HZ));
+        app.init_resource::<Assets<Mesh>>();
+        app.init_resource::<Assets<VehicleProfile>>();
+        app.init_resource::<SpatialIndex>();
+        app.init_resource::<Replay>();
+        app.add_event::<FootPlanted>();
+        app.add_systems(
+            FixedUpdate,
+            (
+                replay_commands,
+                physics::update_vehicle_physics,
+                step_spider_legs,
+                record_states,
+            )
+                .chain(),
+        );
+
+        let vehicle = SpiderData::from_position_and_angle(Vec2::ZERO, 0.0);
+        let mut legs = BTreeMap::new();
+        for (index, corner) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let hip_offset = Transform::from_xyz(corner.0, 1.0, corner.1);
+            let rest_offset = Vec3::new(corner.0, -2.0, corner.1);
+            legs.insert(
+                format!("leg_{}", index),
+                SpiderLeg {
+                    parent: Entity::PLACEHOLDER,
+                    marker: Entity::PLACEHOLDER,
+                    knee: Entity::PLACEHOLDER,
+                    entity: Entity::PLACEHOLDER,
+                    chain: TwoBoneChain {
+                        upper_length: 1.5,
+                        lower_length: 3.0,
+                    },
+                    hip_offset,
+                    rest_offset,
+                    pole: None,
+                    foot: vehicle
+                        .pose_current
+                        .mul_transform(hip_offset)
+                        .transform_point(rest_offset),
+                    state: LegState::Planted,
+                    normal: Vec3::Y,
+                },
+            );
+        }
+        app.world_mut().spawn((
+            vehicle,
+            VehicleCommand::default(),
+            BodyAdaptation::default(),
+            SpiderProfile(Handle::default()),
+            Footprint::default(),
+            NavPath::default(),
+            SpiderAnimation {
+                gltf: Handle::default(),
+                player: None,
+                clips: BTreeMap::new(),
+                rig: Handle::default(),
+                legs,
+                gait: GaitScheduler::default(),
+            },
+        ));
+
+        while app.world().resource::<Replay>().states.len() < TICKS {
+            app.update();
+        }
+        app.world_mut().remove_resource::<Replay>().unwrap()
+    }
+
+    #[test]
+    fn physics_ignores_frame_rate() {
+        let slow = run(TICK_HZ / 2.0);
+        let fast = run(TICK_HZ * 2.0);
+        for (tick, (aa, bb)) in slow.states.iter().zip(&fast.states).take(TICKS).enumerate() {
+            assert!(aa == bb, "states differ at tick {}", tick);
         }
+        // the spider actually moved and stepped
+        let (position, _, _, feet) = &slow.states[TICKS - 1];
+        assert!(*position != Vec2::ZERO);
+        assert!(*feet != slow.states[0].3);
     }
 }
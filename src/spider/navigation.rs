@@ -0,0 +1,127 @@
+use super::data::SpiderData;
+use crate::nav::{self, NavGrid};
+
+use bevy::prelude::*;
+
+use std::collections::VecDeque;
+
+const REPLAN_DISTANCE: f32 = 1.0; // m, target displacement triggering a new plan
+const WAYPOINT_RADIUS: f32 = 2.0; // m, intermediate waypoints are passed within this distance
+const ARRIVE_RADIUS: f32 = 0.5; // m
+const SLOW_RADIUS: f32 = 8.0; // m, starts braking when the goal is closer
+const CRUISE_SPEED: f32 = 12.0; // m / s
+const TURN_GAIN: f32 = 2.0; // 1 / rad
+
+/// Waypoints followed while the target is captured
+#[derive(Component, Default, Debug)]
+pub struct NavPath {
+    pub goal: Option<Vec2>, // none when no plan was requested or no grid is available
+    pub waypoints: VecDeque<Vec2>,
+    pub is_unreachable: bool, // no path to the goal, the waypoints are empty
+}
+
+pub struct Steering {
+    pub thrust: f32,
+    pub brake: f32,
+    pub turn: f32,
+}
+
+impl NavPath {
+    pub fn is_active(&self) -> bool {
+        self.goal.is_some()
+    }
+
+    /// Steering and thrust towards the next waypoint, stops once the path is exhausted
+    pub fn steer(&mut self, pos: Vec2, velocity: Vec2, angle: f32) -> Steering {
+        while self.waypoints.len() > 1 && pos.distance(self.waypoints[0]) < WAYPOINT_RADIUS {
+            self.waypoints.pop_front();
+        }
+        if self.waypoints.len() == 1 && pos.distance(self.waypoints[0]) < ARRIVE_RADIUS {
+            self.waypoints.clear();
+        }
+
+        let Some(waypoint) = self.waypoints.front() else {
+            return stop(velocity, angle);
+        };
+        let remaining = pos.distance(*waypoint)
+            + self
+                .waypoints
+                .iter()
+                .zip(self.waypoints.iter().skip(1))
+                .map(|(aa, bb)| aa.distance(*bb))
+                .sum::<f32>();
+        seek(pos, velocity, angle, *waypoint, remaining)
+    }
+}
+
+/// Brakes until the vehicle stops moving forward
+pub fn stop(velocity: Vec2, angle: f32) -> Steering {
+    let heading = Vec2::from_angle(-angle);
+    Steering {
+        thrust: 0.0,
+        brake: if velocity.dot(heading) > 0.0 {
+            1.0
+        } else {
+            0.0
+        },
+        turn: 0.0,
+    }
+}
+
+/// Turns towards point and drives at a speed decreasing with the remaining distance
+pub fn seek(pos: Vec2, velocity: Vec2, angle: f32, point: Vec2, remaining: f32) -> Steering {
+    let heading = Vec2::from_angle(-angle);
+    let forward_speed = velocity.dot(heading);
+
+    // positive turns increase the angle, rotating the heading clockwise in the xz plane
+    let error = heading.angle_to(point - pos);
+    let turn = (-error * TURN_GAIN).clamp(-1.0, 1.0);
+
+    let desired_speed = CRUISE_SPEED * (remaining / SLOW_RADIUS).clamp(0.1, 1.0);
+    if forward_speed > desired_speed {
+        return Steering {
+            thrust: 0.0,
+            brake: 1.0,
+            turn,
+        };
+    }
+    Steering {
+        thrust: error.cos().max(0.0),
+        brake: 0.0,
+        turn,
+    }
+}
+
+/// Plans again whenever the captured target moves or the grid changes
+pub fn plan_navigation_paths(
+    mut vehicles: Query<(&SpiderData, &mut NavPath)>,
+    nav_grid: Res<NavGrid>,
+) {
+    let Some(grid) = &nav_grid.grid else {
+        return;
+    };
+    for (vehicle, mut path) in &mut vehicles {
+        if !vehicle.is_target_captured || vehicle.surface.is_some() {
+            if path.is_active() {
+                *path = NavPath::default();
+            }
+            continue;
+        }
+        let goal = vehicle.position_target;
+        let is_stale = path
+            .goal
+            .is_none_or(|previous| previous.distance(goal) > REPLAN_DISTANCE);
+        if !is_stale && !nav_grid.is_changed() {
+            continue;
+        }
+        let plan = nav::plan(grid, vehicle.position_current, goal);
+        if plan.is_none() {
+            warn!("no path to {}", goal);
+        }
+        *path = NavPath {
+            goal: Some(goal),
+            is_unreachable: plan.is_none(),
+            waypoints: plan.map(VecDeque::from).unwrap_or_default(),
+        };
+    }
+}
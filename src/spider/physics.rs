@@ -1,10 +1,17 @@
-use super::SpiderData;
+use super::body::BodyAdaptation;
+use super::brain::HumanDriver;
+use super::data::{SpiderData, VehicleCommand, VehicleInput};
+use super::navigation::NavPath;
+use super::profile::{SpiderProfile, VehicleProfile};
+use super::terrain::SpiderPart;
+use crate::actions::{Action, ActionState};
+use crate::collision::{self, Collider, Footprint};
+use crate::players::PlayerRegistry;
+use crate::spatial::SpatialIndex;
 
 use bevy::math::{Mat2, Quat, Vec2, Vec3};
 use bevy::prelude::*;
 
-use std::f32::consts::PI;
-
 struct VehiclePhysics {
     mass: f32,
     friction: Vec2,
@@ -17,16 +24,16 @@ struct VehiclePhysics {
 }
 
 impl VehiclePhysics {
-    fn from_dt(dt: f32) -> Self {
+    fn from_profile_and_dt(profile: &VehicleProfile, dt: f32) -> Self {
         Self {
-            mass: 100.0,                     // kg
-            friction: Vec2::new(1e-2, 5e-2), // 0 <= f < 1
-            thrust: 4000.0,                  // m / s^2 / kg ~ N
-            brake: 4000.0,                   // m / s^2 / kg ~ N
-            turning_speed: 3.5 * PI / 4.0,   // rad / s
-            target_speed: 20.0,              // m / s
-            capture_speed: 8.0,              // 1 / s
-            dt,                              // s
+            mass: profile.mass,
+            friction: profile.friction,
+            thrust: profile.thrust,
+            brake: profile.brake,
+            turning_speed: profile.turning_speed,
+            target_speed: profile.target_speed,
+            capture_speed: profile.capture_speed,
+            dt, // s
         }
     }
 }
@@ -48,62 +55,97 @@ impl VehiclePhysics {
     }
 }
 
+/// Samples the actions of each player every frame, physics latches the result at its own rate
+pub fn read_vehicle_inputs(
+    mut vehicles: Query<(&mut VehicleInput, &HumanDriver)>,
+    registry: Res<PlayerRegistry>,
+    actions: Res<ActionState>,
+) {
+    for (mut vehicle_input, driver) in &mut vehicles {
+        let Some(actions) = registry
+            .source(driver.slot)
+            .and_then(|source| actions.source(source))
+        else {
+            vehicle_input.0 = VehicleCommand::default();
+            continue;
+        };
+
+        // the stick moves the target along the screen diagonals
+        let target_move = actions.axis_pair(Action::MoveTarget);
+        let toggle_capture =
+            vehicle_input.0.toggle_capture ^ actions.just_pressed(Action::ToggleCapture);
+        vehicle_input.0 = VehicleCommand {
+            thrust: actions.axis(Action::Thrust).max(0.0),
+            brake: actions.axis(Action::Brake).max(0.0),
+            turn: actions.axis(Action::Turn),
+            target_move: (Vec2::X + Vec2::Y) * target_move.x + (Vec2::X - Vec2::Y) * target_move.y,
+            toggle_capture,
+        };
+    }
+}
+
+/// Holds the last sampled controls for the whole tick, brains override them afterwards
+pub fn latch_vehicle_inputs(mut vehicles: Query<(&mut VehicleInput, &mut VehicleCommand)>) {
+    for (mut vehicle_input, mut command) in &mut vehicles {
+        *command = vehicle_input.0.clone();
+        vehicle_input.0.toggle_capture = false;
+    }
+}
+
+/// Runs in the fixed schedule, the planar state only depends on the tick and the commands
 pub fn update_vehicle_physics(
-    mut vehicles: Query<(&mut SpiderData, &mut Transform)>,
-    time: Res<Time>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    gamepads: Query<(Entity, &Gamepad)>,
+    mut vehicles: Query<(
+        &mut SpiderData,
+        &mut VehicleCommand,
+        &BodyAdaptation,
+        &SpiderProfile,
+        &Footprint,
+        &mut NavPath,
+    )>,
+    colliders: Query<(&Collider, &GlobalTransform)>,
+    index: Res<SpatialIndex>,
+    profiles: Res<Assets<VehicleProfile>>,
+    mut ray_cast: MeshRayCast,
+    parts: Query<(), With<SpiderPart>>,
+    time: Res<Time<Fixed>>,
 ) {
-    let physics = VehiclePhysics::from_dt(time.delta_secs());
+    let default_profile = VehicleProfile::default();
+    let is_terrain = |entity: Entity| !parts.contains(entity);
 
-    for (mut vehicle, mut transform) in &mut vehicles {
+    for (mut vehicle, mut command, adaptation, profile, footprint, mut path) in &mut vehicles {
+        let profile = profiles.get(&profile.0).unwrap_or(&default_profile);
+        let physics = VehiclePhysics::from_profile_and_dt(profile, time.delta_secs());
+        vehicle.angle_previous = vehicle.angle_current;
+        vehicle.pose_previous = vehicle.pose_current;
+        let angle_prev = vehicle.angle_previous;
         let pos_prev = vehicle.position_previous;
         let pos_current = vehicle.position_current;
         let mut force = Vec2::ZERO;
 
-        {
-            if keyboard.pressed(KeyCode::ArrowLeft) {
-                vehicle.angle_current += physics.turning_speed * physics.dt;
-            }
-            if keyboard.pressed(KeyCode::ArrowRight) {
-                vehicle.angle_current -= physics.turning_speed * physics.dt;
-            }
-            let dir_current = Vec2::from_angle(-vehicle.angle_current);
-            if keyboard.pressed(KeyCode::ArrowUp) {
-                force += physics.thrust * dir_current;
-            }
-            if keyboard.pressed(KeyCode::ArrowDown) {
-                force -= physics.brake * dir_current;
-            }
-            if keyboard.just_pressed(KeyCode::Space) {
-                vehicle.is_target_captured ^= true;
-            }
+        // A captured target with a plan drives through the regular controls
+        let is_following = vehicle.is_target_captured && path.is_active();
+        if is_following {
+            let velocity = (pos_current - pos_prev) / physics.dt;
+            let steering = path.steer(pos_current, velocity, vehicle.angle_current);
+            command.thrust = steering.thrust;
+            command.brake = steering.brake;
+            command.turn = steering.turn;
         }
 
         {
-            for (_, gamepad) in &gamepads {
-                let left_stick_x = gamepad.get(GamepadAxis::LeftStickX).unwrap();
-                let left_stick_y = gamepad.get(GamepadAxis::LeftStickY).unwrap();
-                if left_stick_x.abs() > 0.05 {
-                    let speed = (Vec2::X + Vec2::Y) * physics.target_speed;
-                    vehicle.position_target += speed * left_stick_x * physics.dt;
-                }
-                if left_stick_y.abs() > 0.05 {
-                    let speed = (Vec2::X - Vec2::Y) * physics.target_speed;
-                    vehicle.position_target += speed * left_stick_y * physics.dt;
-                }
-                let right_stick_x = gamepad.get(GamepadAxis::RightStickX).unwrap();
-                if right_stick_x.abs() > 0.05 {
-                    vehicle.angle_current -= physics.turning_speed * right_stick_x * physics.dt;
-                }
-                if gamepad.just_pressed(GamepadButton::South) {
-                    vehicle.is_target_captured ^= true;
-                }
+            vehicle.angle_current += physics.turning_speed * command.turn * physics.dt;
+            let dir_current = Vec2::from_angle(-vehicle.angle_current);
+            force += physics.thrust * command.thrust * dir_current;
+            force -= physics.brake * command.brake * dir_current;
+            vehicle.position_target += physics.target_speed * command.target_move * physics.dt;
+            if command.toggle_capture {
+                vehicle.is_target_captured ^= true;
+                command.toggle_capture = false;
             }
         }
 
-        let pos_next = if vehicle.is_target_captured {
-            // Moves towards target
+        let pos_next = if vehicle.is_target_captured && !is_following {
+            // Moves towards target, used while no navigation grid is available
             let alpha = physics.capture_speed * physics.dt;
             let alpha = alpha.clamp(0.0, 1.0);
             pos_current * (1.0 - alpha) + vehicle.position_target * alpha
@@ -114,8 +156,73 @@ pub fn update_vehicle_physics(
 
         vehicle.position_previous = vehicle.position_current;
         vehicle.position_current = pos_next;
-        transform.translation = lift(pos_next);
-        transform.rotation = Quat::from_axis_angle(Vec3::Y, vehicle.angle_current);
+
+        // Slide along obstacles, crawling spiders climb them instead
+        if vehicle.surface.is_none() {
+            let bottom = vehicle.height_current;
+            let obstacles = collision::gather(&index, &colliders, pos_next, footprint, bottom);
+            if let Some((push, normal)) =
+                collision::resolve(&obstacles, pos_next, footprint, bottom)
+            {
+                // keep the tangential velocity, drop the part going into the obstacle
+                let velocity = pos_next - pos_current;
+                let velocity = velocity - normal * velocity.dot(normal).min(0.0);
+                vehicle.position_current = pos_next + push;
+                vehicle.position_previous = vehicle.position_current - velocity;
+                if vehicle.is_target_captured && !is_following {
+                    vehicle.position_target += push;
+                }
+            }
+        }
+
+        if let Some(mut surface) = vehicle.surface {
+            // Crawl along the surface, the planar state only provides the step
+            surface.turn(vehicle.angle_current - angle_prev);
+            let dir_current = Vec2::from_angle(-vehicle.angle_current);
+            let delta = pos_next - pos_current;
+            let side = surface.forward.cross(surface.up);
+            let step =
+                surface.forward * delta.dot(dir_current) + side * delta.dot(dir_current.perp());
+            if surface.crawl(step, &mut ray_cast, &is_terrain) {
+                vehicle.surface = Some(surface);
+                vehicle.pose_current = Transform::from_translation(
+                    surface.position + surface.up * adaptation.ride_height,
+                )
+                .with_rotation(surface.rotation());
+                continue;
+            }
+            warn!("lost surface contact, dropping to the floor");
+            vehicle.surface = Some(surface);
+            vehicle.detach();
+        }
+
+        // Follow the plane fitted through the planted feet
+        let (alpha_height, alpha_tilt) = adaptation.alphas(physics.dt);
+        let height_target = vehicle.ground_height + adaptation.ride_height;
+        let tilt_target = Quat::from_rotation_arc(Vec3::Y, vehicle.ground_normal);
+        vehicle.height_current += (height_target - vehicle.height_current) * alpha_height;
+        vehicle.tilt_current = vehicle.tilt_current.slerp(tilt_target, alpha_tilt);
+
+        vehicle.pose_current = Transform::from_translation(
+            lift(vehicle.position_current) + Vec3::Y * vehicle.height_current,
+        )
+        .with_rotation(
+            vehicle.tilt_current * Quat::from_axis_angle(Vec3::Y, vehicle.angle_current),
+        );
+    }
+}
+
+/// Renders the body between the last two physics ticks
+pub fn interpolate_vehicle_transforms(
+    mut vehicles: Query<(&SpiderData, &mut Transform)>,
+    time: Res<Time<Fixed>>,
+) {
+    let alpha = time.overstep_fraction();
+    for (vehicle, mut transform) in &mut vehicles {
+        let previous = vehicle.pose_previous;
+        let current = vehicle.pose_current;
+        transform.translation = previous.translation.lerp(current.translation, alpha);
+        transform.rotation = previous.rotation.slerp(current.rotation, alpha);
     }
 }
 
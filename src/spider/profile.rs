@@ -0,0 +1,125 @@
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Handling parameters of a vehicle, tuned without recompiling
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct VehicleProfile {
+    pub mass: f32,          // kg
+    pub friction: Vec2,     // 0 <= f < 1, along and across the heading
+    pub thrust: f32,        // m / s^2 / kg ~ N
+    pub brake: f32,         // m / s^2 / kg ~ N
+    pub turning_speed: f32, // rad / s
+    pub target_speed: f32,  // m / s
+    pub capture_speed: f32, // 1 / s
+}
+
+impl Default for VehicleProfile {
+    fn default() -> Self {
+        Self {
+            mass: 100.0,
+            friction: Vec2::new(1e-2, 5e-2),
+            thrust: 4000.0,
+            brake: 4000.0,
+            turning_speed: 2.75,
+            target_speed: 20.0,
+            capture_speed: 8.0,
+        }
+    }
+}
+
+impl VehicleProfile {
+    /// Lists the values that would make the integration unstable
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.mass.is_nan() || self.mass <= 0.0 {
+            problems.push(format!("non positive mass {}", self.mass));
+        }
+        if !(0.0..1.0).contains(&self.friction.x) || !(0.0..1.0).contains(&self.friction.y) {
+            problems.push(format!("friction {} out of [0, 1)", self.friction));
+        }
+        problems
+    }
+}
+
+/// Handling profile used by a spider, falls back to the default profile while loading
+#[derive(Component, Clone, Debug)]
+pub struct SpiderProfile(pub Handle<VehicleProfile>);
+
+#[derive(Default)]
+pub struct VehicleProfileLoader;
+
+#[derive(Debug, Error)]
+pub enum VehicleProfileLoaderError {
+    #[error("could not read vehicle profile: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse vehicle profile: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid vehicle profile: {}", .0.join(", "))]
+    Invalid(Vec<String>),
+}
+
+impl AssetLoader for VehicleProfileLoader {
+    type Asset = VehicleProfile;
+    type Settings = ();
+    type Error = VehicleProfileLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let profile = ron::de::from_bytes::<VehicleProfile>(&bytes)?;
+        // a rejected reload keeps the previous profile, a rejected first load the default one
+        let problems = profile.validate();
+        if !problems.is_empty() {
+            return Err(VehicleProfileLoaderError::Invalid(problems));
+        }
+        Ok(profile)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vehicle.ron"]
+    }
+}
+
+/// Reports profile (re)loads, physics picks the new values up at the next tick
+pub fn report_profile_changes(
+    mut events: EventReader<AssetEvent<VehicleProfile>>,
+    profiles: Res<Assets<VehicleProfile>>,
+    server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        let Some(profile) = profiles.get(*id) else {
+            continue;
+        };
+        let path = server
+            .get_path(*id)
+            .map(|path| path.to_string())
+            .unwrap_or_default();
+        info!("vehicle profile {} {:?}", path, profile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_profile_matches_asset() {
+        let profile: VehicleProfile =
+            ron::de::from_str(include_str!("../../assets/profiles/default.vehicle.ron")).unwrap();
+        let default = VehicleProfile::default();
+        assert!(default.validate().is_empty());
+        assert_eq!(format!("{:?}", profile), format!("{:?}", default));
+    }
+}
@@ -0,0 +1,143 @@
+use super::gait::{Gait, LegKey};
+use super::locomotion::Locomotion;
+
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use std::collections::BTreeMap;
+
+/// Declares how the legs of a rigged model are driven
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct SpiderRig {
+    pub legs: Vec<LegDescription>,
+    pub gaits: Vec<GaitDescription>,
+    #[serde(default)]
+    pub clips: BTreeMap<Locomotion, String>, // named glTF animation clips
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LegDescription {
+    pub bone: LegKey,      // node driven by the knee, its parent is used as the hip
+    pub upper_length: f32, // m, hip to knee
+    pub lower_length: f32, // m, knee to foot
+    pub rest_offset: Vec3, // m, foot rest position in the hip frame
+    #[serde(default)]
+    pub pole: Option<Vec3>, // knee pull direction in the body frame, up and outward when none
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct GaitDescription {
+    pub gait: Gait,
+    pub groups: Vec<Vec<LegKey>>, // legs lifting together, in stepping order
+}
+
+impl SpiderRig {
+    /// Lists the problems that would prevent the rig from animating properly,
+    /// feet must stay reachable a full step away from their rest position
+    pub fn validate(&self, step_length: f32) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.legs.is_empty() {
+            problems.push("no legs declared".into());
+        }
+        for leg in &self.legs {
+            let reach = leg.upper_length + leg.lower_length;
+            if leg.upper_length <= 0.0 || leg.lower_length <= 0.0 {
+                problems.push(format!("leg {} has non positive chain lengths", leg.bone));
+            } else if reach <= leg.rest_offset.length() + step_length {
+                problems.push(format!(
+                    "leg {} can not reach a step away from its rest position ({} <= {} + {})",
+                    leg.bone,
+                    reach,
+                    leg.rest_offset.length(),
+                    step_length
+                ));
+            }
+            if leg.pole.is_some_and(|pole| pole.length() <= 0.0) {
+                problems.push(format!("leg {} has a zero pole direction", leg.bone));
+            }
+        }
+        for gait in &self.gaits {
+            for bone in gait.groups.iter().flatten() {
+                if !self.legs.iter().any(|leg| leg.bone == *bone) {
+                    problems.push(format!(
+                        "gait {:?} references undeclared leg {}",
+                        gait.gait, bone
+                    ));
+                }
+            }
+        }
+        problems
+    }
+}
+
+#[derive(Default)]
+pub struct SpiderRigLoader;
+
+#[derive(Debug, Error)]
+pub enum SpiderRigLoaderError {
+    #[error("could not read rig: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse rig: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for SpiderRigLoader {
+    type Asset = SpiderRig;
+    type Settings = ();
+    type Error = SpiderRigLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let rig = ron::de::from_bytes::<SpiderRig>(&bytes)?;
+        Ok(rig)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rig.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tachikoma_rig_is_valid() {
+        let rig: SpiderRig =
+            ron::de::from_str(include_str!("../../assets/rigs/tachikoma.rig.ron")).unwrap();
+        assert_eq!(
+            rig.validate(super::super::SPIDER_STEP_LENGTH),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn pole_is_optional() {
+        let rig: SpiderRig = ron::de::from_str(
+            "(legs: [
+                (bone: \"aa\", upper_length: 1.5, lower_length: 3.25, rest_offset: (0.0, 3.5, 0.0)),
+                (bone: \"bb\", upper_length: 1.5, lower_length: 3.25, rest_offset: (0.0, 3.5, 0.0),
+                    pole: Some((0.0, 1.0, 1.0))),
+                (bone: \"cc\", upper_length: 1.5, lower_length: 3.25, rest_offset: (0.0, 3.5, 0.0),
+                    pole: Some((0.0, 0.0, 0.0))),
+            ], gaits: [])",
+        )
+        .unwrap();
+        assert_eq!(rig.legs[0].pole, None);
+        assert_eq!(rig.legs[1].pole, Some(Vec3::new(0.0, 1.0, 1.0)));
+        let problems = rig.validate(super::super::SPIDER_STEP_LENGTH);
+        assert_eq!(
+            problems,
+            vec!["leg cc has a zero pole direction".to_owned()]
+        );
+    }
+}
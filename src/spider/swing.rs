@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+
+#[derive(Clone, Debug, Default)]
+pub enum LegState {
+    #[default]
+    Planted,
+    Swinging(Swing),
+}
+
+#[derive(Clone, Debug)]
+pub struct Swing {
+    start: Vec3,
+    end: Vec3,
+    elapsed: f32, // s
+}
+
+impl Swing {
+    const DURATION: f32 = 0.25; // s
+    const LIFT_HEIGHT: f32 = 0.75; // m
+
+    pub fn new(start: Vec3, end: Vec3) -> Self {
+        Self {
+            start,
+            end,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= Self::DURATION
+    }
+
+    /// Foot position along a parabolic arc, eased in and out along the ground
+    pub fn position(&self, up: Vec3) -> Vec3 {
+        let tt = (self.elapsed / Self::DURATION).clamp(0.0, 1.0);
+        let ss = tt * tt * (3.0 - 2.0 * tt);
+        let lift = 4.0 * Self::LIFT_HEIGHT * tt * (1.0 - tt);
+        self.start.lerp(self.end, ss) + up * lift
+    }
+}
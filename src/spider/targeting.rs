@@ -0,0 +1,152 @@
+use super::brain::HumanDriver;
+use super::data::SpiderData;
+use super::navigation::NavPath;
+use super::terrain;
+use crate::actions::{Action, ActionState, InputSource};
+use crate::nav::NavGround;
+use crate::players::PlayerRegistry;
+
+use bevy::color::palettes::css::*;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+const PICK_DISTANCE: f32 = 1000.0; // m
+const ADVANCE_RADIUS: f32 = 2.0; // m, the next queued target is taken within this distance
+const MARKER_RADIUS: f32 = 1.5; // m
+
+/// Targets picked with shift-click, taken in order once the current target is reached
+#[derive(Component, Default, Debug)]
+pub struct TargetQueue {
+    pub waypoints: VecDeque<Vec2>,
+}
+
+/// Ground point under the cursor, on the ground meshes or else on the y = 0 plane
+fn pick_ground(
+    ray_cast: &mut MeshRayCast,
+    grounds: &Query<(), With<NavGround>>,
+    ray: Ray3d,
+) -> Option<Vec2> {
+    let filter = |entity: Entity| grounds.contains(entity);
+    if let Some(contact) =
+        terrain::cast(ray_cast, &filter, ray.origin, *ray.direction, PICK_DISTANCE)
+    {
+        return Some(contact.point.xz());
+    }
+    let distance = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))?;
+    Some(ray.get_point(distance).xz())
+}
+
+/// Sets the target of the keyboard and mouse player to the clicked ground point
+pub fn pick_targets(
+    mut vehicles: Query<(&mut SpiderData, &mut TargetQueue, &HumanDriver)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    grounds: Query<(), With<NavGround>>,
+    interactions: Query<&Interaction>,
+    mut ray_cast: MeshRayCast,
+    registry: Res<PlayerRegistry>,
+    actions: Res<ActionState>,
+) {
+    let Some(actions) = actions.source(InputSource::KeyboardMouse) else {
+        return;
+    };
+    if !actions.just_pressed(Action::PickTarget) {
+        return;
+    }
+    // clicks on the ui are not meant for the ground
+    if interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.single(), cameras.single()) else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor) else {
+        return;
+    };
+    let Some(point) = pick_ground(&mut ray_cast, &grounds, ray) else {
+        return;
+    };
+
+    let is_queued = actions.pressed(Action::QueueTarget);
+    for (mut vehicle, mut queue, driver) in &mut vehicles {
+        if registry.source(driver.slot) != Some(InputSource::KeyboardMouse) {
+            continue;
+        }
+        if is_queued && vehicle.is_target_captured {
+            debug!("queued target {}", point);
+            queue.waypoints.push_back(point);
+            continue;
+        }
+        debug!("picked target {}", point);
+        queue.waypoints.clear();
+        vehicle.position_target = point;
+        vehicle.is_target_captured = true;
+    }
+}
+
+/// Moves on to the next queued target once the current one is reached,
+/// unreachable targets are dropped and release the capture when nothing is queued
+pub fn advance_target_queue(mut vehicles: Query<(&mut SpiderData, &mut TargetQueue, &NavPath)>) {
+    for (mut vehicle, mut queue, path) in &mut vehicles {
+        if !vehicle.is_target_captured {
+            queue.waypoints.clear();
+            continue;
+        }
+        if path.is_unreachable {
+            warn!("target {} unreachable, dropped", vehicle.position_target);
+            match queue.waypoints.pop_front() {
+                Some(next) => vehicle.position_target = next,
+                None => vehicle.is_target_captured = false,
+            }
+            continue;
+        }
+        let is_reached = vehicle.position_current.distance(vehicle.position_target)
+            < ADVANCE_RADIUS
+            || (path.is_active() && path.waypoints.is_empty());
+        if !is_reached {
+            continue;
+        }
+        if let Some(next) = queue.waypoints.pop_front() {
+            vehicle.position_target = next;
+        }
+    }
+}
+
+pub fn display_target_markers(vehicles: Query<(&SpiderData, &TargetQueue)>, mut gizmos: Gizmos) {
+    let flat = Quat::from_rotation_x(PI / 2.0);
+    let lift = |pos: Vec2| Vec3::new(pos.x, 0.1, pos.y);
+    for (vehicle, queue) in &vehicles {
+        if !vehicle.is_target_captured {
+            continue;
+        }
+        gizmos.circle(
+            Isometry3d::new(lift(vehicle.position_target), flat),
+            MARKER_RADIUS,
+            LIME,
+        );
+        for waypoint in &queue.waypoints {
+            gizmos.circle(
+                Isometry3d::new(lift(*waypoint), flat),
+                MARKER_RADIUS * 0.6,
+                YELLOW,
+            );
+        }
+        if !queue.waypoints.is_empty() {
+            gizmos.linestrip(
+                std::iter::once(vehicle.position_target)
+                    .chain(queue.waypoints.iter().copied())
+                    .map(lift),
+                YELLOW,
+            );
+        }
+    }
+}
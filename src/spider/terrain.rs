@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+
+/// Tags spider rig and effect entities so that terrain queries skip them
+#[derive(Component, Clone, Copy)]
+pub struct SpiderPart;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+const RAY_HEIGHT: f32 = 3.0; // m, how far above the target the probe starts
+const RAY_DEPTH: f32 = 3.0; // m, how far below the target the probe looks
+
+/// Casts a ray along -up through target and returns the first surface hit
+pub fn project(
+    ray_cast: &mut MeshRayCast,
+    filter: &impl Fn(Entity) -> bool,
+    target: Vec3,
+    up: Vec3,
+) -> Option<Contact> {
+    let origin = target + up * RAY_HEIGHT;
+    let contact = cast(ray_cast, filter, origin, -up, RAY_HEIGHT + RAY_DEPTH)?;
+    let normal = if contact.normal.dot(up) < 0.0 {
+        -contact.normal
+    } else {
+        contact.normal
+    };
+    Some(Contact {
+        point: contact.point,
+        normal,
+    })
+}
+
+/// First surface hit along dir within max_distance of origin
+pub fn cast(
+    ray_cast: &mut MeshRayCast,
+    filter: &impl Fn(Entity) -> bool,
+    origin: Vec3,
+    dir: Vec3,
+    max_distance: f32,
+) -> Option<Contact> {
+    let dir = Dir3::new(dir).ok()?;
+    let settings = MeshRayCastSettings::default()
+        .with_visibility(RayCastVisibility::Visible)
+        .with_filter(filter);
+    let hits = ray_cast.cast_ray(Ray3d::new(origin, dir), &settings);
+    let (_, hit) = hits.iter().find(|(_, hit)| hit.distance <= max_distance)?;
+    Some(Contact {
+        point: hit.point,
+        normal: hit.normal.normalize_or(-*dir),
+    })
+}
@@ -33,20 +33,23 @@ pub fn make(frame: &mut EntityCommands<'_>, label: &str) -> Entity {
 }
 
 pub fn update(
-    mut checkboxes: Query<
-        (&Interaction, &mut UiCheckbox, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>),
-    >,
+    mut checkboxes: Query<(&Interaction, &mut UiCheckbox), (Changed<Interaction>, With<Button>)>,
 ) {
-    for (interaction, mut data, mut bg_color) in checkboxes.iter_mut() {
+    for (interaction, mut data) in checkboxes.iter_mut() {
         if matches!(*interaction, Interaction::Pressed) {
             data.checked ^= true;
-            *bg_color = if data.checked {
-                COLOR_UI_BG.into()
-            } else {
-                COLOR_UI_BG_DISABLED.into()
-            };
             info!("***** checkbox {} {}", data.label, data.checked);
         }
     }
 }
+
+/// Follows the checked state, whether it was clicked or set from code
+pub fn draw(mut checkboxes: Query<(&UiCheckbox, &mut BackgroundColor), Changed<UiCheckbox>>) {
+    for (data, mut bg_color) in checkboxes.iter_mut() {
+        *bg_color = if data.checked {
+            COLOR_UI_BG.into()
+        } else {
+            COLOR_UI_BG_DISABLED.into()
+        };
+    }
+}
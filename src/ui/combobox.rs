@@ -40,17 +40,11 @@ pub fn make(frame: &mut EntityCommands<'_>, names: Vec<&str>) -> Entity {
 
 pub fn update(
     mut buttons: Query<
-        (
-            &Interaction,
-            &mut UiCombobox,
-            &Children,
-            &RelativeCursorPosition,
-        ),
+        (&Interaction, &mut UiCombobox, &RelativeCursorPosition),
         (Changed<Interaction>, With<Button>),
     >,
-    mut texts: Query<&mut Text>,
 ) {
-    for (interaction, mut data, children, relative_cursor) in buttons.iter_mut() {
+    for (interaction, mut data, relative_cursor) in buttons.iter_mut() {
         if matches!(*interaction, Interaction::Pressed) {
             let delta = match relative_cursor.normalized {
                 None => 1,
@@ -64,12 +58,21 @@ pub fn update(
             };
             data.index += delta;
             data.index %= data.names.len();
-            let name = data.names[data.index].clone();
+            let name = &data.names[data.index];
             info!("***** combobox {delta} {name}");
-            assert!(children.len() == 3);
-            let label = children[1];
-            let mut text = texts.get_mut(label).unwrap();
-            **text = name.clone();
         }
     }
 }
+
+/// Follows the selected index, whether it was clicked or set from code
+pub fn draw(
+    buttons: Query<(&UiCombobox, &Children), Changed<UiCombobox>>,
+    mut texts: Query<&mut Text>,
+) {
+    for (data, children) in buttons.iter() {
+        assert!(children.len() == 3);
+        let label = children[1];
+        let mut text = texts.get_mut(label).unwrap();
+        **text = data.names[data.index].clone();
+    }
+}
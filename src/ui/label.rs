@@ -0,0 +1,20 @@
+use bevy::prelude::*;
+
+use super::colors::*;
+
+/// Read only text, its entity holds the text to update
+pub fn make(frame: &mut EntityCommands<'_>, text: &str) -> Entity {
+    let mut ret = Option::None;
+    frame.with_children(|parent| {
+        let node = make_default_node();
+        let container = parent.spawn((
+            node.clone(),
+            BorderColor(COLOR_UI_FG.into()),
+            BackgroundColor(COLOR_UI_BG.into()),
+            Text::new(text),
+            TextColor(COLOR_UI_FG.into()),
+        ));
+        ret = Some(container.id());
+    });
+    return ret.unwrap();
+}
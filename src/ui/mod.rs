@@ -1,12 +1,16 @@
 mod checkbox;
 mod colors;
 mod combobox;
+mod label;
 // mod game_done_screen;
 // mod track_selection_menu;
 
+use crate::simu::{RULE_PRESETS, SEED_PRESETS, SIMU_SPEEDS};
+
 use bevy::prelude::*;
 
 use checkbox::UiCheckbox;
+use combobox::UiCombobox;
 
 // pub use game_done_screen::GameDoneScreenPlugin;
 // pub use track_selection_menu::TrackSelectionMenuPlugin;
@@ -16,14 +20,42 @@ pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, populate_ui);
-        app.add_systems(Update, (combobox::update, checkbox::update, update).chain());
+        app.add_systems(
+            Update,
+            (
+                sync,
+                combobox::update,
+                checkbox::update,
+                combobox::draw,
+                checkbox::draw,
+                update,
+            )
+                .chain()
+                .in_set(UiSystems),
+        );
     }
 }
 
+/// Systems other modules writing back into the ui state run before
+#[derive(SystemSet, Hash, Clone, Eq, PartialEq, Debug)]
+pub struct UiSystems;
+
 #[derive(Resource)]
 pub struct UiState {
     toggle_gizmos: Entity,
+    select_gait: Entity,
+    select_rule: Entity,
+    select_seed: Entity,
+    select_simu_speed: Entity,
+    toggle_simu_pause: Entity,
+    label_simu_generation: Entity,
     pub display_gizmos: bool,
+    pub gait_index: usize,
+    pub rule_index: usize,
+    pub seed_index: usize, // changing the seed reseeds the simulation
+    pub simu_speed_index: usize,
+    pub simu_paused: bool,
+    pub simu_generation: u64, // displayed only
 }
 
 fn populate_ui(mut commands: Commands) {
@@ -37,18 +69,80 @@ fn populate_ui(mut commands: Commands) {
         ..default()
     });
 
-    combobox::make(&mut ui_frame, vec!["aa", "bb", "cc"]);
-    combobox::make(&mut ui_frame, vec!["x", "yy", "zzz", "wwww"]);
+    let select_gait = combobox::make(&mut ui_frame, vec!["tripod", "wave", "ripple"]);
+    let select_rule = combobox::make(
+        &mut ui_frame,
+        RULE_PRESETS.iter().map(|(name, _)| *name).collect(),
+    );
+    let select_seed = combobox::make(
+        &mut ui_frame,
+        SEED_PRESETS.iter().map(|(name, _)| *name).collect(),
+    );
+
+    let select_simu_speed = combobox::make(
+        &mut ui_frame,
+        SIMU_SPEEDS.iter().map(|(name, _)| *name).collect(),
+    );
+    let toggle_simu_pause = checkbox::make(&mut ui_frame, "pause");
+    let label_simu_generation = label::make(&mut ui_frame, "");
 
     let toggle_gizmos = checkbox::make(&mut ui_frame, "gizmos");
 
     commands.insert_resource(UiState {
         toggle_gizmos,
+        select_gait,
+        select_rule,
+        select_seed,
+        select_simu_speed,
+        toggle_simu_pause,
+        label_simu_generation,
         display_gizmos: false,
+        gait_index: 0,
+        rule_index: 0,
+        seed_index: 0,
+        simu_speed_index: 0,
+        simu_paused: false,
+        simu_generation: 0,
     });
 }
 
-fn update(mut ui_state: ResMut<UiState>, checkboxes: Query<&UiCheckbox>) {
-    let foo = checkboxes.get(ui_state.toggle_gizmos).unwrap();
-    ui_state.display_gizmos = foo.checked;
+/// Pushes values written by other modules into the widgets, before clicks are handled
+fn sync(
+    ui_state: Res<UiState>,
+    mut checkboxes: Query<&mut UiCheckbox>,
+    mut comboboxes: Query<&mut UiCombobox>,
+    mut texts: Query<&mut Text>,
+) {
+    let mut pause_checkbox = checkboxes.get_mut(ui_state.toggle_simu_pause).unwrap();
+    if pause_checkbox.checked != ui_state.simu_paused {
+        pause_checkbox.checked = ui_state.simu_paused;
+    }
+    let mut speed_combobox = comboboxes.get_mut(ui_state.select_simu_speed).unwrap();
+    if speed_combobox.index != ui_state.simu_speed_index {
+        speed_combobox.index = ui_state.simu_speed_index;
+    }
+    let mut generation_label = texts.get_mut(ui_state.label_simu_generation).unwrap();
+    let generation = format!("generation {}", ui_state.simu_generation);
+    if **generation_label != generation {
+        **generation_label = generation;
+    }
+}
+
+fn update(
+    mut ui_state: ResMut<UiState>,
+    checkboxes: Query<&UiCheckbox>,
+    comboboxes: Query<&UiCombobox>,
+) {
+    let gizmos_checkbox = checkboxes.get(ui_state.toggle_gizmos).unwrap();
+    ui_state.display_gizmos = gizmos_checkbox.checked;
+    let gait_combobox = comboboxes.get(ui_state.select_gait).unwrap();
+    ui_state.gait_index = gait_combobox.index;
+    let rule_combobox = comboboxes.get(ui_state.select_rule).unwrap();
+    ui_state.rule_index = rule_combobox.index;
+    let seed_combobox = comboboxes.get(ui_state.select_seed).unwrap();
+    ui_state.seed_index = seed_combobox.index;
+    let speed_combobox = comboboxes.get(ui_state.select_simu_speed).unwrap();
+    ui_state.simu_speed_index = speed_combobox.index;
+    let pause_checkbox = checkboxes.get(ui_state.toggle_simu_pause).unwrap();
+    ui_state.simu_paused = pause_checkbox.checked;
 }